@@ -1,6 +1,27 @@
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, Utc};
+use pyo3::buffer::PyBuffer;
 use pyo3::prelude::*;
+use pyo3::types::{
+    PyBool, PyByteArray, PyBytes, PyDate, PyDateAccess, PyDateTime, PyDict, PyFloat, PyList,
+    PyLong, PyString, PyTime, PyTimeAccess, PyTuple,
+};
+use tokio::sync::mpsc;
 
 use rorm;
+use rorm::row::Row;
+use rorm::value::Value;
+
+use crate::cursor;
+use crate::errors;
+use crate::introspection;
+use crate::postgres;
+use crate::transaction;
+use crate::types;
 
 /**
 Enum of the different database row types
@@ -13,13 +34,1016 @@ pub(crate) enum DatabaseValueType {
     I64,
     I32,
     I16,
+    U64,
+    U32,
     Bool,
     F64,
     F32,
+    Decimal,
+    Json,
     Binary,
+    Uuid,
     NaiveTime,
     NaiveDate,
     NaiveDateTime,
+    DateTimeUtc,
+}
+
+impl DatabaseValueType {
+    /**
+    Retrieve the singleton class attribute of this variant to be used as DB API type code
+     */
+    pub(crate) fn type_code(self, py: Python) -> PyResult<PyObject> {
+        let variant = format!("{:?}", self);
+        Ok(py
+            .get_type::<DatabaseValueType>()
+            .getattr(variant.as_str())?
+            .into_py(py))
+    }
+
+    /**
+    Infer the type of a column by decoding its values in the given rows
+
+    The first non-NULL value of the column determines its type. Columns which
+    only contain NULL values (or result sets without any row) are of type Null.
+    A column missing in the result raises ProgrammingError, a value which can't be
+    decoded as any of the types raises DataError instead of being dropped silently.
+     */
+    pub(crate) fn infer(rows: &[Row], column: &str) -> PyResult<Self> {
+        for row in rows {
+            let mut missing = false;
+            macro_rules! probe {
+                ( $t:ty, $variant:expr ) => {
+                    match row.get::<Option<$t>, &str>(column) {
+                        Ok(Some(_)) => return Ok($variant),
+                        Ok(None) => continue,
+                        Err(rorm::error::Error::SqlxError(sqlx::Error::ColumnNotFound(_))) => {
+                            missing = true
+                        }
+                        Err(_) => {}
+                    }
+                };
+            }
+            // Postgres checks the type strictly, so the narrower types have to be probed, too
+            probe!(i64, DatabaseValueType::I64);
+            if missing {
+                return Err(errors::ProgrammingError::new_err(format!(
+                    "Column {} doesn't exist in the result set",
+                    column
+                )));
+            }
+            probe!(i32, DatabaseValueType::I32);
+            probe!(i16, DatabaseValueType::I16);
+            probe!(f64, DatabaseValueType::F64);
+            probe!(f32, DatabaseValueType::F32);
+            probe!(bool, DatabaseValueType::Bool);
+            probe!(&str, DatabaseValueType::String);
+            probe!(
+                sqlx::types::Json<serde_json::Value>,
+                DatabaseValueType::Json
+            );
+            probe!(Vec<u8>, DatabaseValueType::Binary);
+            probe!(uuid::Uuid, DatabaseValueType::Uuid);
+            probe!(chrono::NaiveDateTime, DatabaseValueType::NaiveDateTime);
+            probe!(
+                chrono::DateTime<chrono::Utc>,
+                DatabaseValueType::DateTimeUtc
+            );
+            probe!(chrono::NaiveDate, DatabaseValueType::NaiveDate);
+            probe!(chrono::NaiveTime, DatabaseValueType::NaiveTime);
+            return Err(errors::DataError::new_err(format!(
+                "Can't infer the type of column {}, specify it as (name, DatabaseValueType)",
+                column
+            )));
+        }
+        Ok(DatabaseValueType::Null)
+    }
+}
+
+impl FromStr for DatabaseValueType {
+    type Err = PyErr;
+
+    /**
+    Parse the name of a variant like `I64` or `naivedatetime`, which is case-insensitive
+     */
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let lowercase = name.to_lowercase();
+        DATABASE_VALUE_TYPES
+            .iter()
+            .find(|v| format!("{:?}", v).to_lowercase() == lowercase)
+            .copied()
+            .ok_or_else(|| {
+                errors::ProgrammingError::new_err(format!(
+                    "Unknown value type {}, expected one of {}",
+                    name,
+                    DATABASE_VALUE_TYPES
+                        .iter()
+                        .map(|v| format!("{:?}", v))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
+static DATABASE_VALUE_TYPES: [DatabaseValueType; 18] = [
+    DatabaseValueType::Null,
+    DatabaseValueType::String,
+    DatabaseValueType::I64,
+    DatabaseValueType::I32,
+    DatabaseValueType::I16,
+    DatabaseValueType::U64,
+    DatabaseValueType::U32,
+    DatabaseValueType::Bool,
+    DatabaseValueType::F64,
+    DatabaseValueType::F32,
+    DatabaseValueType::Decimal,
+    DatabaseValueType::Json,
+    DatabaseValueType::Binary,
+    DatabaseValueType::Uuid,
+    DatabaseValueType::NaiveTime,
+    DatabaseValueType::NaiveDate,
+    DatabaseValueType::NaiveDateTime,
+    DatabaseValueType::DateTimeUtc,
+];
+
+#[pymethods]
+impl DatabaseValueType {
+    /**
+    Look up a value type by its case-insensitive name, e.g. `DatabaseValueType.from_name("i64")`
+     */
+    #[staticmethod]
+    fn from_name(name: &str) -> PyResult<Self> {
+        name.parse()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("DatabaseValueType.{:?}", self)
+    }
+}
+
+/**
+Column of a result set given by its name and optionally by its forced type
+
+The type can be given as DatabaseValueType or by its name, see `DatabaseValueType.from_name`.
+ */
+#[derive(Clone, Debug)]
+pub(crate) enum ColumnSpec {
+    Typed(String, DatabaseValueType),
+    Name(String),
+}
+
+impl ColumnSpec {
+    /**
+    Resolve the types of the columns, inferring the types of columns given only by name
+     */
+    pub(crate) fn resolve(
+        columns: Vec<ColumnSpec>,
+        rows: &[Row],
+    ) -> PyResult<Vec<(String, DatabaseValueType)>> {
+        columns
+            .into_iter()
+            .map(|spec| match spec {
+                ColumnSpec::Typed(name, col_t) => Ok((name, col_t)),
+                ColumnSpec::Name(name) => {
+                    let col_t = DatabaseValueType::infer(rows, &name)?;
+                    Ok((name, col_t))
+                }
+            })
+            .collect()
+    }
+
+    /**
+    Ensure that a spec exists for every column of the result set, if it has any rows
+
+    rorm's rows don't expose the names of their columns, so they can't be decoded without a
+    spec. The number of columns is detected by probing the index after the last given column.
+     */
+    pub(crate) fn check_complete(count: usize, rows: &[Row]) -> PyResult<()> {
+        let row = match rows.first() {
+            Some(row) => row,
+            None => return Ok(()),
+        };
+        match row.get::<Option<i64>, usize>(count) {
+            Err(rorm::error::Error::SqlxError(sqlx::Error::ColumnIndexOutOfBounds { .. })) => {
+                Ok(())
+            }
+            _ if count == 0 => Err(errors::ProgrammingError::new_err(
+                "The query returned rows, but no columns were given. Specify them as names \
+                or (name, DatabaseValueType) tuples, since rorm rows don't expose their names",
+            )),
+            _ => Err(errors::ProgrammingError::new_err(format!(
+                "The result set has more than the {} given columns, specify all of them",
+                count
+            ))),
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for ColumnSpec {
+    fn extract(obj: &'source PyAny) -> PyResult<Self> {
+        if let Ok(name) = obj.extract::<String>() {
+            return Ok(ColumnSpec::Name(name));
+        }
+        let (name, col_t): (String, &PyAny) = obj.extract()?;
+        let col_t = match col_t.extract::<DatabaseValueType>() {
+            Ok(v) => v,
+            Err(_) => col_t.extract::<&str>()?.parse()?,
+        };
+        Ok(ColumnSpec::Typed(name, col_t))
+    }
+}
+
+/**
+Read-only and contiguous Python buffer of a binary parameter, which is bound without a copy
+
+The buffer is exported for as long as it's held, so its memory stays valid even without the GIL.
+ */
+#[derive(Clone)]
+pub(crate) struct SharedBuffer(Arc<PyBuffer<u8>>);
+
+impl SharedBuffer {
+    /**
+    Share the buffer, unless it's writable or not contiguous and has to be copied instead
+     */
+    pub(crate) fn new(buffer: PyBuffer<u8>) -> Result<Self, PyBuffer<u8>> {
+        if buffer.readonly() && buffer.is_c_contiguous() {
+            Ok(SharedBuffer(Arc::new(buffer)))
+        } else {
+            Err(buffer)
+        }
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        if self.0.len_bytes() == 0 {
+            return &[];
+        }
+        // Safety: the buffer is read-only and contiguous, as checked by `new`, and stays exported
+        // until the last clone drops it
+        unsafe { std::slice::from_raw_parts(self.0.buf_ptr() as *const u8, self.0.len_bytes()) }
+    }
+}
+
+impl std::fmt::Debug for SharedBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SharedBuffer({} bytes)", self.0.len_bytes())
+    }
+}
+
+/**
+Owned value of a query parameter, since the rorm values only borrow their data
+ */
+#[derive(Clone, Debug)]
+pub(crate) enum DatabaseValue {
+    Null,
+    String(String),
+    I64(i64),
+    Bool(bool),
+    F64(f64),
+    Decimal(String),
+    Json(String),
+    Binary(Vec<u8>),
+    Buffer(SharedBuffer),
+    Uuid(uuid::Uuid),
+    NaiveTime(NaiveTime),
+    NaiveDate(NaiveDate),
+    NaiveDateTime(NaiveDateTime),
+    DateTimeUtc(DateTime<Utc>),
+    List(Vec<DatabaseValue>),
+}
+
+impl DatabaseValue {
+    /**
+    Borrow the owned value as value which can be bound to a rorm query
+     */
+    pub(crate) fn as_value(&self) -> Value<'_> {
+        match self {
+            DatabaseValue::Null => Value::Null,
+            DatabaseValue::String(v) => Value::String(v),
+            DatabaseValue::I64(v) => Value::I64(*v),
+            DatabaseValue::Bool(v) => Value::Bool(*v),
+            DatabaseValue::F64(v) => Value::F64(*v),
+            DatabaseValue::Decimal(v) => Value::String(v),
+            DatabaseValue::Json(v) => Value::String(v),
+            DatabaseValue::Binary(v) => Value::Binary(v),
+            DatabaseValue::Buffer(v) => Value::Binary(v.as_bytes()),
+            DatabaseValue::Uuid(v) => Value::Binary(v.as_bytes()),
+            DatabaseValue::NaiveTime(v) => Value::NaiveTime(*v),
+            DatabaseValue::NaiveDate(v) => Value::NaiveDate(*v),
+            DatabaseValue::NaiveDateTime(v) => Value::NaiveDateTime(*v),
+            DatabaseValue::DateTimeUtc(v) => Value::NaiveDateTime(v.naive_utc()),
+            // Lists are expanded by expand_params before any value is bound
+            DatabaseValue::List(_) => Value::Null,
+        }
+    }
+
+    /**
+    Replace the values which sqlx's Any driver can't bind for Postgres by their text representation
+
+    UUIDs would be bound as bytea, which can't be cast to uuid, and aware datetimes as
+    timestamp without time zone, which Postgres interprets in the session time zone.
+    As text, UUIDs and datetimes with their explicit offset need a cast, e.g. `?::uuid`.
+     */
+    pub(crate) fn into_postgres(self) -> DatabaseValue {
+        match self {
+            DatabaseValue::Uuid(v) => DatabaseValue::String(v.to_string()),
+            DatabaseValue::DateTimeUtc(v) => {
+                DatabaseValue::String(v.to_rfc3339_opts(SecondsFormat::AutoSi, false))
+            }
+            v => v,
+        }
+    }
+
+    /**
+    Convert the value back to the Python object it has been extracted from
+     */
+    pub(crate) fn to_py(&self, py: Python) -> PyResult<PyObject> {
+        Ok(match self {
+            DatabaseValue::Null => py.None(),
+            DatabaseValue::String(v) => v.into_py(py),
+            DatabaseValue::I64(v) => v.into_py(py),
+            DatabaseValue::Bool(v) => v.into_py(py),
+            DatabaseValue::F64(v) => v.into_py(py),
+            DatabaseValue::Decimal(v) => types::decimal_to_py(py, v)?,
+            DatabaseValue::Json(v) => types::json_to_py(py, v, true)?,
+            DatabaseValue::Binary(v) => PyBytes::new(py, v).into_py(py),
+            DatabaseValue::Buffer(v) => PyBytes::new(py, v.as_bytes()).into_py(py),
+            DatabaseValue::Uuid(v) => types::uuid_to_py(py, v)?,
+            DatabaseValue::NaiveTime(v) => types::time_to_py(py, *v)?.into_py(py),
+            DatabaseValue::NaiveDate(v) => types::date_to_py(py, *v)?.into_py(py),
+            DatabaseValue::NaiveDateTime(v) => types::datetime_to_py(py, *v)?.into_py(py),
+            DatabaseValue::DateTimeUtc(v) => types::datetime_utc_to_py(py, *v)?.into_py(py),
+            DatabaseValue::List(v) => v
+                .iter()
+                .map(|v| v.to_py(py))
+                .collect::<PyResult<Vec<PyObject>>>()?
+                .into_py(py),
+        })
+    }
+}
+
+impl<'source> FromPyObject<'source> for DatabaseValue {
+    fn extract(obj: &'source PyAny) -> PyResult<Self> {
+        py_to_db_value(obj)
+    }
+}
+
+/**
+Build a chrono date from the date fields of a Python date or datetime
+ */
+fn py_to_date(obj: &impl PyDateAccess) -> PyResult<NaiveDate> {
+    NaiveDate::from_ymd_opt(obj.get_year(), obj.get_month() as u32, obj.get_day() as u32)
+        .ok_or_else(|| errors::DataError::new_err("Date out of range"))
+}
+
+/**
+Build a chrono time from the time fields of a Python time or datetime
+ */
+fn py_to_time(obj: &impl PyTimeAccess) -> PyResult<NaiveTime> {
+    NaiveTime::from_hms_micro_opt(
+        obj.get_hour() as u32,
+        obj.get_minute() as u32,
+        obj.get_second() as u32,
+        obj.get_microsecond(),
+    )
+    .ok_or_else(|| errors::DataError::new_err("Time out of range"))
+}
+
+/**
+Convert a Python object to a value which can be bound as parameter to a query
+
+Decimals are bound as their exact string representation, since rorm has no numeric value.
+Dicts and objects wrapped in `Json` are bound as their JSON serialization, lists and tuples
+are expanded to one placeholder per item, see `expand_params`. Read-only buffers like bytes
+or their memoryviews are bound without a copy, writable ones like bytearray are copied.
+UUIDs are bound as their 16 bytes, which matches BINARY(16) columns. Timezone-aware datetimes
+are converted to UTC and bound as naive timestamps, since rorm has no timezone-aware value.
+On Postgres, both are bound as text instead, which needs a cast like `?::uuid` or
+`?::timestamptz`, see `DatabaseValue::into_postgres`.
+Raises NotSupportedError for objects of unknown types.
+ */
+pub(crate) fn py_to_db_value(obj: &PyAny) -> PyResult<DatabaseValue> {
+    // Bool has to be checked before int, since bool is a subclass of int in Python,
+    // the same applies to datetime which is a subclass of date
+    if obj.is_none() {
+        Ok(DatabaseValue::Null)
+    } else if obj.is_instance_of::<PyBool>()? {
+        Ok(DatabaseValue::Bool(obj.extract()?))
+    } else if obj.is_instance_of::<PyLong>()? {
+        Ok(DatabaseValue::I64(obj.extract()?))
+    } else if obj.is_instance_of::<PyFloat>()? {
+        Ok(DatabaseValue::F64(obj.extract()?))
+    } else if obj.is_instance_of::<PyString>()? {
+        Ok(DatabaseValue::String(obj.extract()?))
+    } else if obj.is_instance(types::decimal_type(obj.py())?)? {
+        Ok(DatabaseValue::Decimal(obj.str()?.extract()?))
+    } else if obj.is_instance(types::uuid_type(obj.py())?)? {
+        let bytes: &[u8] = obj.getattr("bytes")?.extract()?;
+        Ok(DatabaseValue::Uuid(uuid::Uuid::from_slice(bytes).map_err(
+            |err| errors::DataError::new_err(err.to_string()),
+        )?))
+    } else if obj.is_instance_of::<PyDict>()? {
+        Ok(DatabaseValue::Json(types::py_to_json(obj)?))
+    } else if let Ok(v) = obj.downcast::<PyCell<types::Json>>() {
+        Ok(DatabaseValue::Json(v.borrow().dumps(obj.py())?))
+    } else if obj.is_instance_of::<PyList>()? || obj.is_instance_of::<PyTuple>()? {
+        Ok(DatabaseValue::List(obj.extract()?))
+    } else if let Ok(v) = obj.downcast::<PyByteArray>() {
+        Ok(DatabaseValue::Binary(v.to_vec()))
+    } else if let Ok(v) = obj.downcast::<PyDateTime>() {
+        if !v.call_method0("utcoffset")?.is_none() {
+            let utc = types::utc(obj.py())?;
+            let v: &PyDateTime = v.call_method1("astimezone", (utc,))?.downcast()?;
+            let naive = NaiveDateTime::new(py_to_date(v)?, py_to_time(v)?);
+            return Ok(DatabaseValue::DateTimeUtc(naive.and_utc()));
+        }
+        Ok(DatabaseValue::NaiveDateTime(NaiveDateTime::new(
+            py_to_date(v)?,
+            py_to_time(v)?,
+        )))
+    } else if let Ok(v) = obj.downcast::<PyDate>() {
+        Ok(DatabaseValue::NaiveDate(py_to_date(v)?))
+    } else if let Ok(v) = obj.downcast::<PyTime>() {
+        Ok(DatabaseValue::NaiveTime(py_to_time(v)?))
+    } else if let Ok(buffer) = PyBuffer::<u8>::get(obj) {
+        // Bytes and other objects supporting the buffer protocol with byte items,
+        // e.g. memoryview, are bound without a copy if the buffer can't change meanwhile
+        match SharedBuffer::new(buffer) {
+            Ok(buffer) => Ok(DatabaseValue::Buffer(buffer)),
+            Err(buffer) => Ok(DatabaseValue::Binary(buffer.to_vec(obj.py())?)),
+        }
+    } else {
+        Err(errors::NotSupportedError::new_err(format!(
+            "Unsupported parameter type {}",
+            obj.get_type().name()?
+        )))
+    }
+}
+
+/**
+Kind of a character of SQL text as classified by `scan_sql`
+ */
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum SqlChar {
+    Code,
+    Quoted,
+    Comment,
+}
+
+/**
+Find the end of a dollar-quoted string like `$$...$$` or `$tag$...$tag$` starting at `start`
+
+Returns None if there is no dollar quote at `start`. Tags can't start with a digit,
+so numbered placeholders like `$1` aren't mistaken for one.
+ */
+fn dollar_quote_end(chars: &[char], start: usize) -> Option<usize> {
+    if start > 0 && (chars[start - 1].is_alphanumeric() || chars[start - 1] == '_') {
+        return None;
+    }
+    let mut tag_end = start + 1;
+    while tag_end < chars.len() && (chars[tag_end].is_alphanumeric() || chars[tag_end] == '_') {
+        if tag_end == start + 1 && chars[tag_end].is_ascii_digit() {
+            return None;
+        }
+        tag_end += 1;
+    }
+    if chars.get(tag_end) != Some(&'$') {
+        return None;
+    }
+    let tag = &chars[start..=tag_end];
+    let mut i = tag_end + 1;
+    while i + tag.len() <= chars.len() {
+        if &chars[i..i + tag.len()] == tag {
+            return Some(i + tag.len());
+        }
+        i += 1;
+    }
+    Some(chars.len())
+}
+
+/**
+Classify each character of SQL text as code, quoted or comment
+
+Quoted strings and identifiers use `'`, `"` or `` ` ``, Postgres' dollar-quoted strings
+are recognized as well. Comments are `-- ...` until the end of the line and `/* ... */`.
+Unterminated quotes and comments reach until the end of the text.
+ */
+pub(crate) fn scan_sql(query: &str) -> Vec<(char, SqlChar)> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut result = Vec::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (end, kind) = match c {
+            '\'' | '"' | '`' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|v| *v == c)
+                    .map_or(chars.len(), |v| i + v + 2);
+                (end, SqlChar::Quoted)
+            }
+            '-' if next == Some('-') => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|v| *v == '\n')
+                    .map_or(chars.len(), |v| i + v);
+                (end, SqlChar::Comment)
+            }
+            '/' if next == Some('*') => {
+                let end = chars[i + 2..]
+                    .windows(2)
+                    .position(|v| v == ['*', '/'])
+                    .map_or(chars.len(), |v| i + v + 4);
+                (end, SqlChar::Comment)
+            }
+            '$' => match dollar_quote_end(&chars, i) {
+                Some(end) => (end, SqlChar::Quoted),
+                None => (i + 1, SqlChar::Code),
+            },
+            _ => (i + 1, SqlChar::Code),
+        };
+        result.extend(chars[i..end].iter().map(|v| (*v, kind)));
+        i = end;
+    }
+    result
+}
+
+/**
+Count the qmark placeholders of a query, ignoring question marks in quotes and comments
+ */
+pub(crate) fn count_placeholders(query: &str) -> usize {
+    scan_sql(query)
+        .iter()
+        .filter(|(c, kind)| *c == '?' && *kind == SqlChar::Code)
+        .count()
+}
+
+/**
+Count the numbered placeholders like `$1` of a query, which is the highest number used
+
+Numbered placeholders are only supported by Postgres, which doesn't support qmark placeholders.
+ */
+pub(crate) fn count_numbered_placeholders(query: &str) -> usize {
+    let mut count = 0;
+    let mut chars = scan_sql(query).into_iter().peekable();
+    while let Some((c, kind)) = chars.next() {
+        if c != '$' || kind != SqlChar::Code {
+            continue;
+        }
+        let mut number = String::new();
+        while let Some((digit, _)) = chars
+            .peek()
+            .filter(|(v, kind)| v.is_ascii_digit() && *kind == SqlChar::Code)
+        {
+            number.push(*digit);
+            chars.next();
+        }
+        if let Ok(n) = number.parse::<usize>() {
+            count = count.max(n);
+        }
+    }
+    count
+}
+
+/**
+Rewrite the qmark placeholders of a query to the numbered placeholders used by Postgres
+
+Question marks in quotes and comments are kept, like in `count_placeholders`.
+ */
+pub(crate) fn number_placeholders(query: &str) -> String {
+    let mut result = String::with_capacity(query.len());
+    let mut count = 0;
+    for (c, kind) in scan_sql(query) {
+        if c == '?' && kind == SqlChar::Code {
+            count += 1;
+            result.push_str(&format!("${}", count));
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/**
+Expand list parameters to one placeholder per item, e.g. for `WHERE id IN (?)`
+
+Empty lists are replaced by NULL, so `IN (NULL)` matches nothing without being a syntax error.
+Nested lists raise ProgrammingError.
+ */
+pub(crate) fn expand_params(
+    query: String,
+    params: Vec<DatabaseValue>,
+) -> PyResult<(String, Vec<DatabaseValue>)> {
+    if !params.iter().any(|v| matches!(v, DatabaseValue::List(_))) {
+        return Ok((query, params));
+    }
+    let mut expanded_query = String::with_capacity(query.len());
+    let mut expanded_params = Vec::with_capacity(params.len());
+    let mut params = params.into_iter();
+    for (c, kind) in scan_sql(&query) {
+        if c != '?' || kind != SqlChar::Code {
+            expanded_query.push(c);
+            continue;
+        }
+        match params.next() {
+            Some(DatabaseValue::List(values)) => {
+                if values.is_empty() {
+                    expanded_query.push_str("NULL");
+                } else {
+                    expanded_query.push_str(&vec!["?"; values.len()].join(", "));
+                }
+                for value in values {
+                    if let DatabaseValue::List(_) = value {
+                        return Err(errors::ProgrammingError::new_err(
+                            "Nested lists can't be used as parameter",
+                        ));
+                    }
+                    expanded_params.push(value);
+                }
+            }
+            Some(value) => {
+                expanded_query.push(c);
+                expanded_params.push(value);
+            }
+            None => expanded_query.push(c),
+        }
+    }
+    Ok((expanded_query, expanded_params))
+}
+
+/**
+Split a script into its semicolon-separated statements, ignoring semicolons in quotes and comments
+
+Semicolons within the `BEGIN ... END` body of a CREATE TRIGGER statement don't split either.
+Statements consisting of comments only are dropped.
+ */
+pub(crate) fn split_statements(script: &str) -> Vec<String> {
+    let mut statements = vec![];
+    let mut current = String::new();
+    let mut has_code = false;
+    let mut word = String::new();
+    let mut trigger = false;
+    let mut depth = 0usize;
+    for (c, kind) in scan_sql(script) {
+        let code = kind == SqlChar::Code;
+        if code && (c.is_alphanumeric() || c == '_') {
+            word.push(c);
+        } else if !word.is_empty() {
+            // CASE ... END may appear within the body of a trigger, too
+            match word.to_ascii_uppercase().as_str() {
+                "TRIGGER" => trigger = true,
+                "BEGIN" | "CASE" if trigger => depth += 1,
+                "END" if trigger => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            word.clear();
+        }
+        if code && c == ';' && depth == 0 {
+            if has_code {
+                statements.push(current.trim().to_string());
+            }
+            current.clear();
+            has_code = false;
+            trigger = false;
+            continue;
+        }
+        has_code |= kind != SqlChar::Comment && !c.is_whitespace();
+        current.push(c);
+    }
+    if has_code {
+        statements.push(current.trim().to_string());
+    }
+    statements
+}
+
+/**
+Ensure that the number of parameters matches the number of placeholders of a query
+
+Queries are written with qmark placeholders, which are rewritten for Postgres.
+Queries for Postgres may use its numbered placeholders like `$1` instead.
+ */
+pub(crate) fn check_params(query: &str, params: &[DatabaseValue]) -> PyResult<()> {
+    let placeholders = match count_placeholders(query) {
+        0 => count_numbered_placeholders(query),
+        v => v,
+    };
+    if placeholders != params.len() {
+        return Err(errors::ProgrammingError::new_err(format!(
+            "Query has {} placeholders, but {} parameters were supplied",
+            placeholders,
+            params.len()
+        )));
+    }
+    Ok(())
+}
+
+/**
+Validate a database configuration, raising ProgrammingError for obvious misconfigurations
+
+This catches invalid pool sizes and empty required fields before attempting to connect.
+Hosts given as socket path raise NotSupportedError instead of failing the DNS lookup.
+ */
+pub(crate) fn check_configuration(configuration: &rorm::DatabaseConfiguration) -> PyResult<()> {
+    if configuration.max_connections == 0 {
+        return Err(errors::ProgrammingError::new_err(
+            "max_connections must be at least 1",
+        ));
+    }
+    if configuration.min_connections > configuration.max_connections {
+        return Err(errors::ProgrammingError::new_err(format!(
+            "min_connections ({}) must not exceed max_connections ({})",
+            configuration.min_connections, configuration.max_connections
+        )));
+    }
+    let required = match &configuration.driver {
+        rorm::DatabaseDriver::SQLite { filename } => vec![("filename", filename)],
+        rorm::DatabaseDriver::MySQL {
+            name, host, user, ..
+        }
+        | rorm::DatabaseDriver::Postgres {
+            name, host, user, ..
+        } => vec![("database", name), ("host", host), ("user", user)],
+    };
+    for (field, value) in required {
+        if value.is_empty() {
+            return Err(errors::ProgrammingError::new_err(format!(
+                "{} must not be empty",
+                field
+            )));
+        }
+    }
+    // TODO: rorm always connects via TCP, a socket path would end up as hostname
+    if let rorm::DatabaseDriver::MySQL { host, .. } | rorm::DatabaseDriver::Postgres { host, .. } =
+        &configuration.driver
+    {
+        if host.starts_with('/') {
+            return Err(errors::NotSupportedError::new_err(format!(
+                "Connecting via the Unix socket {} is not supported by rorm yet",
+                host
+            )));
+        }
+    }
+    Ok(())
+}
+
+static SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/**
+Drive a future to completion on the tokio runtime of pyo3_asyncio, blocking the calling thread
+
+The GIL is released while waiting, so other Python threads keep running. On the main thread,
+pending signals are checked periodically, so Ctrl-C raises KeyboardInterrupt and drops the
+future, which cancels its query and returns the connection to the pool. Python only delivers
+signals to the main thread, so blocking calls on other threads can't be interrupted and don't
+reacquire the GIL until they have finished.
+ */
+pub(crate) fn block_on<F, T>(py: Python, future: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send,
+    T: Send,
+{
+    let threading = py.import("threading")?;
+    let main_thread = threading
+        .call_method0("current_thread")?
+        .is(threading.call_method0("main_thread")?);
+    py.allow_threads(|| {
+        let runtime = pyo3_asyncio::tokio::get_runtime();
+        if main_thread {
+            runtime.block_on(until_signal(future, || {
+                Python::with_gil(|py| py.check_signals())
+            }))
+        } else {
+            runtime.block_on(future)
+        }
+    })
+}
+
+/**
+Await a future while calling `check` periodically, an error of `check` drops the future
+ */
+async fn until_signal<F, T>(future: F, mut check: impl FnMut() -> PyResult<()>) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>>,
+{
+    tokio::pin!(future);
+    loop {
+        tokio::select! {
+            result = &mut future => return result,
+            _ = tokio::time::sleep(SIGNAL_CHECK_INTERVAL) => check()?,
+        }
+    }
+}
+
+static POOL_CLOSE_INTERVAL: Duration = Duration::from_millis(10);
+
+/**
+Close a connection pool once all other handles, e.g. of running queries, have been dropped
+
+rorm can only close a pool it owns, so the handle is polled until it's the last one.
+ */
+pub(crate) async fn close_pool(mut db: Arc<rorm::Database>) {
+    loop {
+        match Arc::try_unwrap(db) {
+            Ok(db) => return db.close().await,
+            Err(shared) => {
+                db = shared;
+                tokio::time::sleep(POOL_CLOSE_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/**
+Target to execute queries on, either the connection pool, a running transaction
+or the implicit transaction of a database which isn't in autocommit mode
+ */
+pub(crate) enum Target {
+    Pool(Arc<rorm::Database>),
+    Transaction(mpsc::UnboundedSender<transaction::Command>),
+    Implicit(Py<Database>),
+}
+
+/**
+Executor of queries on a target, which reports every executed query to the optional logger
+ */
+pub(crate) struct Executor {
+    pub(crate) target: Target,
+    pub(crate) backend: Backend,
+    pub(crate) logger: Option<PyObject>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) slow_query_threshold: Option<Duration>,
+}
+
+impl Executor {
+    /**
+    Execute a raw SQL query with the given parameters and return its rows
+
+    A query exceeding the timeout raises an OperationalError. Its connection goes back to
+    the pool, which tests it before handing it out again and closes it if it's broken.
+    The same applies when the awaitable is cancelled, since pyo3_asyncio drops the future.
+    Within transactions, the query keeps running in the transaction's task, so the
+    transaction should be rolled back.
+     */
+    pub(crate) async fn raw_sql(
+        &self,
+        query: String,
+        params: Vec<DatabaseValue>,
+    ) -> PyResult<Vec<Row>> {
+        let (query, params) = expand_params(query, params)?;
+        let (query, params) = match self.backend {
+            Backend::Postgres => (
+                number_placeholders(&query),
+                params
+                    .into_iter()
+                    .map(DatabaseValue::into_postgres)
+                    .collect(),
+            ),
+            Backend::SQLite | Backend::MySQL => (query, params),
+        };
+        let logged = self
+            .logger
+            .as_ref()
+            .map(|_| (query.clone(), params.clone()));
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "execute",
+            query = query.as_str(),
+            rows = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        let slow_query = self.slow_query_threshold.map(|_| query.clone());
+        let start = Instant::now();
+        let execution = async {
+            match &self.target {
+                Target::Pool(db) => {
+                    let values: Vec<Value> = params.iter().map(|v| v.as_value()).collect();
+                    db.raw_sql(&query, Some(&values), None)
+                        .await
+                        .map_err(errors::map_rorm_error)
+                }
+                Target::Transaction(sender) => transaction::execute(sender, query, params).await,
+                Target::Implicit(database) => {
+                    let sender = Database::implicit_transaction(database).await?;
+                    transaction::execute(&sender, query, params).await
+                }
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let execution = tracing::Instrument::instrument(execution, span.clone());
+        let result = match self.timeout {
+            None => execution.await,
+            Some(timeout) => match tokio::time::timeout(timeout, execution).await {
+                Ok(result) => result,
+                Err(_) => Err(errors::OperationalError::new_err(format!(
+                    "Query timeout, the query took longer than {}ms",
+                    timeout.as_millis()
+                ))),
+            },
+        };
+        let elapsed = start.elapsed();
+        #[cfg(feature = "tracing")]
+        {
+            span.record("duration_ms", elapsed.as_secs_f64() * 1000.0);
+            if let Ok(rows) = &result {
+                span.record("rows", rows.len());
+            }
+        }
+        if let (Some(logger), Some((query, params))) = (&self.logger, &logged) {
+            let duration_ms = elapsed.as_secs_f64() * 1000.0;
+            Python::with_gil(|py| log_query(py, logger, query, params, duration_ms));
+        }
+        if let (Some(threshold), Some(query)) = (self.slow_query_threshold, slow_query) {
+            if elapsed > threshold {
+                Python::with_gil(|py| warn_slow_query(py, &query, elapsed));
+            }
+        }
+        result
+    }
+}
+
+/**
+Raise a Warning for a query which exceeded the slow query threshold
+
+Like exceptions of the query logger, a Warning turned into an exception is printed.
+ */
+fn warn_slow_query(py: Python, query: &str, elapsed: Duration) {
+    let message = format!(
+        "Slow query took {:.1}ms: {}",
+        elapsed.as_secs_f64() * 1000.0,
+        query
+    );
+    if let Err(err) = PyErr::warn(py, py.get_type::<errors::Warning>(), &message, 1) {
+        err.print(py);
+    }
+}
+
+/**
+Invoke the query logger with the executed query, its parameters and its duration in milliseconds
+
+Exceptions raised by the logger are printed instead of failing the query.
+ */
+fn log_query(
+    py: Python,
+    logger: &PyObject,
+    query: &str,
+    params: &[DatabaseValue],
+    duration_ms: f64,
+) {
+    let result = params
+        .iter()
+        .map(|v| v.to_py(py))
+        .collect::<PyResult<Vec<PyObject>>>()
+        .and_then(|params| logger.call1(py, (query, params, duration_ms)));
+    if let Err(err) = result {
+        err.print(py);
+    }
+}
+
+static DEFAULT_RETRY_BACKOFF_MS: u64 = 100;
+
+/**
+Copy a configuration, since rorm's configuration doesn't implement Clone
+ */
+pub(crate) fn copy_configuration(
+    configuration: &rorm::DatabaseConfiguration,
+) -> rorm::DatabaseConfiguration {
+    rorm::DatabaseConfiguration {
+        driver: configuration.driver.clone(),
+        min_connections: configuration.min_connections,
+        max_connections: configuration.max_connections,
+    }
+}
+
+/**
+Kind of database behind a connection, used to choose backend-specific SQL
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Backend {
+    SQLite,
+    MySQL,
+    Postgres,
+}
+
+impl Backend {
+    pub(crate) fn of(driver: &rorm::DatabaseDriver) -> Self {
+        match driver {
+            rorm::DatabaseDriver::SQLite { .. } => Backend::SQLite,
+            rorm::DatabaseDriver::MySQL { .. } => Backend::MySQL,
+            rorm::DatabaseDriver::Postgres { .. } => Backend::Postgres,
+        }
+    }
+
+    /**
+    Name of the backend as exposed to Python
+     */
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Backend::SQLite => "sqlite",
+            Backend::MySQL => "mysql",
+            Backend::Postgres => "postgres",
+        }
+    }
 }
 
 /**
@@ -27,5 +1051,1009 @@ Wrapper class around Rust-specific database functionality
  */
 #[pyclass(module = "rorm_python")]
 pub(crate) struct Database {
-    pub(crate) db: Box<rorm::Database>,
+    pub(crate) db: Option<Arc<rorm::Database>>,
+    pub(crate) backend: Backend,
+    pub(crate) configuration: rorm::DatabaseConfiguration,
+    pub(crate) query_logger: Option<PyObject>,
+    pub(crate) slow_query_threshold: Option<Duration>,
+    pub(crate) autocommit: bool,
+    pub(crate) implicit: Option<mpsc::UnboundedSender<transaction::Command>>,
+    /**
+    Connection kept open outside of the pool, so a shared in-memory SQLite database
+    isn't dropped when the pool closes its last connection
+     */
+    pub(crate) keep_alive: Option<sqlx::SqliteConnection>,
+}
+
+impl Database {
+    /**
+    Connect to the database described by the configuration
+
+    The optional timeout in seconds bounds every attempt to open the pool with its initial
+    connections and raises an OperationalError when it's exceeded. It doesn't apply to
+    acquiring a connection from the pool for a query later on, which rorm configures itself;
+    bound those waits with the `timeout_ms` of `Cursor.execute` instead.
+    The configuration is validated first, see `check_configuration`.
+    Failed attempts are retried up to `connect_retries` times, waiting `retry_backoff_ms`
+    milliseconds before the first retry and doubling the delay for every further one.
+     */
+    pub(crate) async fn connect(
+        configuration: rorm::DatabaseConfiguration,
+        connect_timeout: Option<f64>,
+        connect_retries: Option<u32>,
+        retry_backoff_ms: Option<u64>,
+    ) -> PyResult<Self> {
+        let db = Database::open_pool(
+            &configuration,
+            connect_timeout,
+            connect_retries,
+            retry_backoff_ms,
+        )
+        .await?;
+        Ok(Database {
+            db: Some(Arc::new(db)),
+            backend: Backend::of(&configuration.driver),
+            configuration,
+            query_logger: None,
+            slow_query_threshold: None,
+            autocommit: true,
+            implicit: None,
+            keep_alive: None,
+        })
+    }
+
+    /**
+    Open the connection pool described by the configuration with the options of `connect`
+     */
+    async fn open_pool(
+        configuration: &rorm::DatabaseConfiguration,
+        connect_timeout: Option<f64>,
+        connect_retries: Option<u32>,
+        retry_backoff_ms: Option<u64>,
+    ) -> PyResult<rorm::Database> {
+        check_configuration(configuration)?;
+        let timeout = match connect_timeout {
+            Some(timeout) if !timeout.is_finite() || timeout <= 0.0 => {
+                return Err(errors::ProgrammingError::new_err(
+                    "The connect timeout must be a positive number of seconds",
+                ));
+            }
+            Some(timeout) => Some(timeout),
+            None => None,
+        };
+        let retries = connect_retries.unwrap_or(0);
+        let mut backoff =
+            Duration::from_millis(retry_backoff_ms.unwrap_or(DEFAULT_RETRY_BACKOFF_MS));
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let attempt_configuration = copy_configuration(configuration);
+            match Database::connect_once(attempt_configuration, timeout).await {
+                Ok(v) => return Ok(v),
+                Err(err) if retries == 0 => return Err(err),
+                Err(err) if attempt > retries => {
+                    let final_err = errors::OperationalError::new_err(format!(
+                        "Connecting to the database failed after {} attempts: {}",
+                        attempt, err
+                    ));
+                    Python::with_gil(|py| final_err.set_cause(py, Some(err)));
+                    return Err(final_err);
+                }
+                Err(_) => {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+
+    /**
+    Make a single attempt to connect to the database, respecting the optional timeout in seconds
+     */
+    async fn connect_once(
+        configuration: rorm::DatabaseConfiguration,
+        timeout: Option<f64>,
+    ) -> PyResult<rorm::Database> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "connect",
+            driver = Backend::of(&configuration.driver).name(),
+            duration_ms = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        let start = Instant::now();
+        let connecting = rorm::Database::connect(configuration);
+        #[cfg(feature = "tracing")]
+        let connecting = tracing::Instrument::instrument(connecting, span.clone());
+        let result = match timeout {
+            None => connecting.await.map_err(errors::map_rorm_error),
+            Some(timeout) => {
+                let duration = Duration::from_secs_f64(timeout);
+                match tokio::time::timeout(duration, connecting).await {
+                    Ok(result) => result.map_err(errors::map_rorm_error),
+                    Err(_) => Err(errors::OperationalError::new_err(format!(
+                        "Connecting to the database timed out after {}s",
+                        timeout
+                    ))),
+                }
+            }
+        };
+        #[cfg(feature = "tracing")]
+        span.record("duration_ms", start.elapsed().as_secs_f64() * 1000.0);
+        result
+    }
+
+    /**
+    Retrieve the implicit transaction of the database, starting it if none is running yet
+
+    When two queries start the implicit transaction concurrently, the one which loses the
+    race rolls its transaction back and joins the other one.
+     */
+    pub(crate) async fn implicit_transaction(
+        database: &Py<Database>,
+    ) -> PyResult<mpsc::UnboundedSender<transaction::Command>> {
+        let (running, db) = Python::with_gil(|py| {
+            let database = database.borrow(py);
+            Ok::<_, PyErr>((database.implicit.clone(), database.db()?))
+        })?;
+        if let Some(sender) = running {
+            return Ok(sender);
+        }
+        let sender = transaction::start(db).await?;
+        let running = Python::with_gil(|py| {
+            let mut database = database.borrow_mut(py);
+            match &database.implicit {
+                Some(running) => Some(running.clone()),
+                None => {
+                    database.implicit = Some(sender.clone());
+                    None
+                }
+            }
+        });
+        if let Some(running) = running {
+            transaction::finish(sender, false).await?;
+            return Ok(running);
+        }
+        Ok(sender)
+    }
+
+    /**
+    Finish the implicit transaction, which is a no-op if none is running
+     */
+    fn finish_implicit(
+        &mut self,
+        commit: bool,
+    ) -> PyResult<impl Future<Output = PyResult<()>> + Send + 'static> {
+        self.db()?;
+        let sender = self.implicit.take();
+        Ok(async move {
+            match sender {
+                Some(sender) => transaction::finish(sender, commit).await,
+                None => Ok(()),
+            }
+        })
+    }
+
+    /**
+    Prepare the execution of a script of semicolon-separated statements in a transaction
+
+    The transaction is rolled back when a statement fails, see `executescript`.
+     */
+    pub(crate) fn script(
+        &self,
+        py: Python,
+        script: String,
+    ) -> PyResult<impl Future<Output = PyResult<()>> + Send + 'static> {
+        let db = self.db()?;
+        let backend = self.backend;
+        let logger = self.logger(py);
+        let slow_query_threshold = self.slow_query_threshold;
+        let statements = split_statements(&script);
+        Ok(async move {
+            let sender = transaction::start(db).await?;
+            let executor = Executor {
+                target: Target::Transaction(sender.clone()),
+                backend,
+                logger,
+                timeout: None,
+                slow_query_threshold,
+            };
+            for (index, statement) in statements.into_iter().enumerate() {
+                if let Err(err) = executor.raw_sql(statement, vec![]).await {
+                    transaction::finish(sender, false).await?;
+                    return Err(errors::with_context(
+                        err,
+                        &format!("Failed to execute statement {}", index),
+                    ));
+                }
+            }
+            transaction::finish(sender, true).await
+        })
+    }
+
+    /**
+    Retrieve a new reference to the query logger, if one has been set
+     */
+    pub(crate) fn logger(&self, py: Python) -> Option<PyObject> {
+        self.query_logger.as_ref().map(|v| v.clone_ref(py))
+    }
+
+    /**
+    Retrieve the executor of queries, which targets the implicit transaction unless autocommit is on
+     */
+    pub(crate) fn executor(slf: PyRef<Self>, py: Python) -> PyResult<Executor> {
+        let db = slf.db()?;
+        let backend = slf.backend;
+        let logger = slf.logger(py);
+        let slow_query_threshold = slf.slow_query_threshold;
+        let autocommit = slf.autocommit;
+        let database: Py<Database> = slf.into();
+        Ok(Executor {
+            target: if autocommit {
+                Target::Pool(db)
+            } else {
+                Target::Implicit(database)
+            },
+            backend,
+            logger,
+            timeout: None,
+            slow_query_threshold,
+        })
+    }
+
+    /**
+    Take the connection pool for closing it, so every further use raises InterfaceError
+
+    A pending implicit transaction is dropped, which rolls it back.
+     */
+    fn release(&mut self) -> PyResult<Arc<rorm::Database>> {
+        let db = self.db()?;
+        self.db = None;
+        self.implicit = None;
+        self.keep_alive = None;
+        Ok(db)
+    }
+
+    /**
+    Retrieve a handle to the connection pool, unless the database has been closed
+     */
+    pub(crate) fn db(&self) -> PyResult<Arc<rorm::Database>> {
+        match &self.db {
+            Some(db) => Ok(db.clone()),
+            None => Err(errors::InterfaceError::new_err(
+                "Database has already been closed",
+            )),
+        }
+    }
+}
+
+#[pymethods]
+impl Database {
+    /**
+    Representation like `Database(driver=sqlite, closed=False)`, which never contains credentials
+     */
+    fn __repr__(&self) -> String {
+        format!(
+            "Database(driver={}, closed={})",
+            self.backend.name(),
+            if self.db.is_none() { "True" } else { "False" }
+        )
+    }
+
+    /**
+    Whether the database has been closed
+     */
+    #[getter]
+    fn is_closed(&self) -> bool {
+        self.db.is_none()
+    }
+
+    /**
+    Name of the database backend, which is one of `"sqlite"`, `"mysql"` and `"postgres"`
+     */
+    #[getter]
+    fn driver(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /**
+    Configuration the database has been connected with as dict, which is also used by `reconnect`
+
+    The dict contains the `driver`, the pool size and the fields of the driver, like
+    `filename` for SQLite. The password is always redacted as `"***"`.
+     */
+    #[getter]
+    fn configuration<'p>(&self, py: Python<'p>) -> PyResult<&'p PyDict> {
+        let result = PyDict::new(py);
+        result.set_item("driver", self.backend.name())?;
+        match &self.configuration.driver {
+            rorm::DatabaseDriver::SQLite { filename } => {
+                result.set_item("filename", filename)?;
+            }
+            rorm::DatabaseDriver::MySQL {
+                name,
+                host,
+                port,
+                user,
+                ..
+            }
+            | rorm::DatabaseDriver::Postgres {
+                name,
+                host,
+                port,
+                user,
+                ..
+            } => {
+                result.set_item("name", name)?;
+                result.set_item("host", host)?;
+                result.set_item("port", port)?;
+                result.set_item("user", user)?;
+                result.set_item("password", "***")?;
+            }
+        }
+        result.set_item("min_connections", self.configuration.min_connections)?;
+        result.set_item("max_connections", self.configuration.max_connections)?;
+        Ok(result)
+    }
+
+    /**
+    Whether queries of cursors created by the database are committed immediately (the default)
+
+    When disabled, the queries are executed in an implicit transaction, which is started by
+    the first query and finished by `commit()` or `rollback()`. Unlike most DB API drivers,
+    autocommit is enabled by default to keep the behavior of the connection pool.
+     */
+    #[getter]
+    fn autocommit(&self) -> bool {
+        self.autocommit
+    }
+
+    #[setter]
+    fn set_autocommit(&mut self, value: bool) -> PyResult<()> {
+        if self.implicit.is_some() {
+            return Err(errors::ProgrammingError::new_err(
+                "Can't change autocommit while a transaction is pending, commit or roll back first",
+            ));
+        }
+        self.autocommit = value;
+        Ok(())
+    }
+
+    /**
+    Commit the implicit transaction of a database which isn't in autocommit mode
+
+    Like in `sqlite3`, this is a no-op if there is nothing to commit, e.g. in autocommit mode.
+     */
+    fn commit<'p>(&mut self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let future = self.finish_implicit(true)?;
+        pyo3_asyncio::tokio::future_into_py(py, future)
+    }
+
+    /**
+    Commit the implicit transaction like `commit`, but block until it has been committed
+     */
+    fn commit_blocking(&mut self, py: Python) -> PyResult<()> {
+        let future = self.finish_implicit(true)?;
+        block_on(py, future)
+    }
+
+    /**
+    Roll back the implicit transaction of a database which isn't in autocommit mode
+
+    This is a no-op if there is nothing to roll back.
+     */
+    fn rollback<'p>(&mut self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let future = self.finish_implicit(false)?;
+        pyo3_asyncio::tokio::future_into_py(py, future)
+    }
+
+    /**
+    Roll back the implicit transaction like `rollback`, but block until it has been rolled back
+     */
+    fn rollback_blocking(&mut self, py: Python) -> PyResult<()> {
+        let future = self.finish_implicit(false)?;
+        block_on(py, future)
+    }
+
+    /**
+    Set a callable invoked as `callback(sql, params, duration_ms)` after every executed query
+
+    The callback is invoked for failed queries, too. Pass None to disable the logger again.
+     */
+    fn set_query_logger(&mut self, callback: Option<PyObject>) {
+        self.query_logger = callback;
+    }
+
+    /**
+    Raise a Warning for every query which takes longer than the threshold in milliseconds
+
+    The duration is measured like for the query logger. Pass None to disable it again (the default).
+     */
+    fn set_slow_query_threshold_ms(&mut self, threshold: Option<u64>) {
+        self.slow_query_threshold = threshold.map(Duration::from_millis);
+    }
+
+    /**
+    Close the connection pool, the returned awaitable resolves once all connections are closed
+
+    Further uses of the database raise InterfaceError immediately. Running queries and open
+    transactions still hold the pool, so closing waits for them to finish first.
+    A pending implicit transaction is rolled back, like the DB API requires.
+     */
+    fn close<'p>(&mut self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let db = self.release()?;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            close_pool(db).await;
+            Ok(())
+        })
+    }
+
+    /**
+    Close the database like `close` without an event loop, blocking until the pool is closed
+     */
+    fn close_blocking(mut slf: PyRefMut<Self>, py: Python) -> PyResult<()> {
+        let db = slf.release()?;
+        drop(slf);
+        block_on(py, async move {
+            close_pool(db).await;
+            Ok(())
+        })
+    }
+
+    /**
+    Replace the connection pool by a new one, e.g. after the server has been restarted
+
+    The pool is rebuilt from the original configuration, the query logger and other settings
+    are kept. The old pool is closed first and a pending implicit transaction is dropped,
+    which rolls it back. The connect options are the same as for the connect functions.
+    PRAGMAs applied by `sqlite.connect` aren't applied again. Closed databases can be
+    reconnected, too. The returned awaitable resolves to None.
+     */
+    fn reconnect<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        connect_timeout: Option<f64>,
+        connect_retries: Option<u32>,
+        retry_backoff_ms: Option<u64>,
+    ) -> PyResult<&'p PyAny> {
+        let configuration = copy_configuration(&slf.configuration);
+        let database: Py<Database> = slf.into();
+        let old = {
+            let mut database = database.borrow_mut(py);
+            database.implicit = None;
+            database.db.take()
+        };
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            if let Some(old) = old {
+                close_pool(old).await;
+            }
+            let db = Database::open_pool(
+                &configuration,
+                connect_timeout,
+                connect_retries,
+                retry_backoff_ms,
+            )
+            .await?;
+            Python::with_gil(|py| database.borrow_mut(py).db = Some(Arc::new(db)));
+            Ok(())
+        })
+    }
+
+    /**
+    Enter the database as async context manager
+
+    Use it as `async with await sqlite.connect(...) as db:`.
+     */
+    fn __aenter__<'p>(slf: PyRef<'p, Self>, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let database: Py<Database> = slf.into();
+        pyo3_asyncio::tokio::future_into_py(py, async move { Ok(database) })
+    }
+
+    /**
+    Close the database when leaving the context, regardless of any propagating exception
+     */
+    fn __aexit__<'p>(
+        &mut self,
+        py: Python<'p>,
+        _exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> PyResult<&'p PyAny> {
+        if self.db.is_none() {
+            return pyo3_asyncio::tokio::future_into_py(py, async move { Ok(()) });
+        }
+        self.close(py)
+    }
+
+    /**
+    Create a new cursor to execute queries on this database
+     */
+    fn cursor(slf: PyRef<Self>) -> PyResult<cursor::Cursor> {
+        slf.db()?;
+        Ok(cursor::Cursor::new(slf.into(), None))
+    }
+
+    /**
+    Check that the database is reachable, the returned awaitable resolves to True
+
+    A dead connection raises an OperationalError. `SELECT 1` is a valid
+    liveness query on all supported databases.
+     */
+    fn ping<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let db = self.db()?;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            db.raw_sql("SELECT 1", None, None)
+                .await
+                .map_err(errors::map_rorm_error)?;
+            Ok(true)
+        })
+    }
+
+    /**
+    Execute a query and decode all of its rows, the returned awaitable resolves to a list of dicts
+
+    This is a shortcut for executing the query on a new cursor with the Dict row factory and
+    fetching all rows. The columns are specified like for `Cursor.execute`, a column which is
+    missing in the result raises ProgrammingError. Columns given only by name are checked when
+    their type is inferred, typed ones when they are decoded, so neither is checked without rows.
+    Result columns without a spec raise ProgrammingError as well, see `ColumnSpec::check_complete`.
+     */
+    fn query<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        sql: String,
+        params: Option<Vec<DatabaseValue>>,
+        columns: Vec<ColumnSpec>,
+    ) -> PyResult<&'p PyAny> {
+        slf.db()?;
+        if columns.is_empty() {
+            return Err(errors::ProgrammingError::new_err(
+                "At least one column has to be specified",
+            ));
+        }
+        let params = params.unwrap_or_default();
+        check_params(&sql, &params)?;
+        let executor = Database::executor(slf, py)?;
+        let backend = executor.backend;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let rows = executor.raw_sql(sql, params).await?;
+            ColumnSpec::check_complete(columns.len(), &rows)?;
+            let columns = ColumnSpec::resolve(columns, &rows)?;
+            Python::with_gil(|py| {
+                let columns: Vec<(&str, DatabaseValueType)> = columns
+                    .iter()
+                    .map(|(name, col_t)| (name.as_str(), *col_t))
+                    .collect();
+                let mut result = Vec::with_capacity(rows.len());
+                for row in &rows {
+                    result.push(convert_row!(py, row, backend, columns).into_py(py));
+                }
+                Ok::<_, PyErr>(result)
+            })
+        })
+    }
+
+    /**
+    Query the version of the database server
+
+    The returned awaitable resolves to `((major, minor, patch), raw)`, where `raw` is the
+    version string reported by the server. For SQLite, this is the version of the linked library.
+     */
+    fn server_version<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let db = self.db()?;
+        let backend = self.backend;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let rows = db
+                .raw_sql(introspection::server_version_query(backend), None, None)
+                .await
+                .map_err(errors::map_rorm_error)?;
+            let raw: String = match rows.first() {
+                Some(row) => row
+                    .get::<&str, &str>("version")
+                    .map_err(errors::map_rorm_error)?
+                    .to_string(),
+                None => {
+                    return Err(errors::InterfaceError::new_err(
+                        "The server didn't report its version",
+                    ))
+                }
+            };
+            Ok((introspection::parse_server_version(backend, &raw)?, raw))
+        })
+    }
+
+    /**
+    List the names of all tables, the returned awaitable resolves to a sorted list
+
+    On Postgres and MySQL, the tables can be filtered by their schema, which defaults to the
+    current schema respectively database. SQLite raises NotSupportedError for a schema.
+    With autocommit disabled, the query runs in the implicit transaction and sees its tables.
+     */
+    fn list_tables<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        schema: Option<String>,
+    ) -> PyResult<&'p PyAny> {
+        let (query, params) = introspection::list_tables_query(slf.backend, schema)?;
+        let executor = Database::executor(slf, py)?;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let rows = executor.raw_sql(query, params).await?;
+            introspection::table_names(&rows)
+        })
+    }
+
+    /**
+    Describe the columns of a table, the returned awaitable resolves to a list of tuples
+
+    Every column is described as `(name, DatabaseValueType, nullable, default)`, where the
+    default is the SQL expression as text or None. Native types without a matching
+    DatabaseValueType fall back to String and raise a Warning.
+     */
+    fn describe_table<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        name: String,
+    ) -> PyResult<&'p PyAny> {
+        let backend = slf.backend;
+        let executor = Database::executor(slf, py)?;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let rows = executor
+                .raw_sql(
+                    introspection::describe_table_query(backend).to_string(),
+                    vec![DatabaseValue::String(name.clone())],
+                )
+                .await?;
+            Python::with_gil(|py| introspection::table_columns(py, backend, &name, &rows))
+        })
+    }
+
+    /**
+    Execute a script of semicolon-separated statements, like `sqlite3.Cursor.executescript`
+
+    The statements are executed one after another within a transaction, which is rolled back
+    when a statement fails. The returned awaitable resolves to None. With autocommit disabled,
+    the statements are part of the implicit transaction instead and are neither committed nor
+    rolled back, which is left to `commit` and `rollback`.
+     */
+    fn executescript<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        script: String,
+    ) -> PyResult<&'p PyAny> {
+        if slf.autocommit {
+            let script = slf.script(py, script)?;
+            return pyo3_asyncio::tokio::future_into_py(py, script);
+        }
+        let executor = Database::executor(slf, py)?;
+        let statements = split_statements(&script);
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            for (index, statement) in statements.into_iter().enumerate() {
+                if let Err(err) = executor.raw_sql(statement, vec![]).await {
+                    return Err(errors::with_context(
+                        err,
+                        &format!("Failed to execute statement {}", index),
+                    ));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /**
+    Listen for notifications on a Postgres channel, the awaitable resolves to an async iterator
+
+    The listener uses its own connection, use it as `async for payload in await db.listen(...)`.
+    SQLite and MySQL raise NotSupportedError.
+     */
+    fn listen<'p>(&self, py: Python<'p>, channel: String) -> PyResult<&'p PyAny> {
+        self.db()?;
+        if self.backend != Backend::Postgres {
+            return Err(errors::NotSupportedError::new_err(
+                "LISTEN is only supported by Postgres",
+            ));
+        }
+        let driver = self.configuration.driver.clone();
+        pyo3_asyncio::tokio::future_into_py(py, postgres::listen(driver, channel))
+    }
+
+    /**
+    Bulk load rows into a Postgres table using COPY, the returned awaitable resolves to their count
+
+    The rows can be any iterable of sequences with one value per column, which is consumed in
+    batches while copying. The values are converted like query parameters. COPY uses its own
+    connection and isn't part of the implicit transaction. SQLite and MySQL raise NotSupportedError.
+     */
+    fn copy_records<'p>(
+        &self,
+        py: Python<'p>,
+        table: String,
+        columns: Vec<String>,
+        rows: &PyAny,
+    ) -> PyResult<&'p PyAny> {
+        self.db()?;
+        if self.backend != Backend::Postgres {
+            return Err(errors::NotSupportedError::new_err(
+                "COPY is only supported by Postgres",
+            ));
+        }
+        let rows: PyObject = rows.iter()?.into_py(py);
+        let driver = self.configuration.driver.clone();
+        pyo3_asyncio::tokio::future_into_py(
+            py,
+            postgres::copy_records(driver, table, columns, rows),
+        )
+    }
+
+    /**
+    Send a notification with the payload to all listeners of a Postgres channel
+
+    With autocommit disabled, the notification is part of the implicit transaction and only
+    delivered once it has been committed. SQLite and MySQL raise NotSupportedError.
+     */
+    fn notify<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        channel: String,
+        payload: String,
+    ) -> PyResult<&'p PyAny> {
+        if slf.backend != Backend::Postgres {
+            return Err(errors::NotSupportedError::new_err(
+                "NOTIFY is only supported by Postgres",
+            ));
+        }
+        let executor = Database::executor(slf, py)?;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            executor
+                .raw_sql(
+                    "SELECT pg_notify(?, ?)".to_string(),
+                    vec![
+                        DatabaseValue::String(channel),
+                        DatabaseValue::String(payload),
+                    ],
+                )
+                .await?;
+            Ok(())
+        })
+    }
+
+    /**
+    Start a new transaction, the returned awaitable resolves to a Transaction
+
+    The isolation level defaults to the server's default. Postgres supports all standard
+    levels, SQLite only SERIALIZABLE, which is what it always uses. MySQL only accepts the
+    level before the transaction has been started, which rorm doesn't allow, so any level
+    raises NotSupportedError there. Explicit transactions are only available to async code,
+    synchronous code can disable autocommit and use `commit_blocking` instead.
+     */
+    fn begin<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        isolation_level: Option<String>,
+    ) -> PyResult<&'p PyAny> {
+        let db = slf.db()?;
+        let statement = match isolation_level {
+            Some(level) => transaction::isolation_statement(slf.backend, &level)?,
+            None => None,
+        };
+        let backend = slf.backend;
+        let logger = slf.logger(py);
+        let slow_query_threshold = slf.slow_query_threshold;
+        let database: Py<Database> = slf.into();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let sender = transaction::start(db).await?;
+            if let Some(statement) = statement {
+                let executor = Executor {
+                    target: Target::Transaction(sender.clone()),
+                    backend,
+                    logger,
+                    timeout: None,
+                    slow_query_threshold,
+                };
+                if let Err(err) = executor.raw_sql(statement, vec![]).await {
+                    transaction::finish(sender, false).await?;
+                    return Err(err);
+                }
+            }
+            Ok(transaction::Transaction {
+                database,
+                sender: Some(sender),
+                savepoints: 0,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    struct DropGuard(Arc<std::sync::atomic::AtomicBool>);
+
+    impl Drop for DropGuard {
+        fn drop(&mut self) {
+            self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn until_signal_drops_the_future_on_errors() {
+        let dropped = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let guard = DropGuard(dropped.clone());
+        let future = async move {
+            let _guard = guard;
+            std::future::pending::<PyResult<()>>().await
+        };
+        let mut checks = 0;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(until_signal(future, || {
+            checks += 1;
+            match checks {
+                1 => Ok(()),
+                _ => Err(pyo3::exceptions::PyKeyboardInterrupt::new_err(())),
+            }
+        }));
+        assert!(result.is_err());
+        assert_eq!(checks, 2);
+        assert!(dropped.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[test]
+    fn until_signal_returns_the_result() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(until_signal(async { Ok(1) }, || {
+            panic!("The future is ready before the first check")
+        }));
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[test]
+    fn count_placeholders_ignores_quoted_text() {
+        assert_eq!(count_placeholders("SELECT ? , ?"), 2);
+        assert_eq!(count_placeholders("SELECT '?', \"?\", `?` WHERE a = ?"), 1);
+        assert_eq!(count_placeholders("SELECT 1"), 0);
+    }
+
+    #[test]
+    fn count_placeholders_ignores_comments_and_dollar_quotes() {
+        assert_eq!(count_placeholders("SELECT ? -- why?\n, ?"), 2);
+        assert_eq!(count_placeholders("SELECT /* ? */ ?"), 1);
+        assert_eq!(count_placeholders("SELECT $$?$$, $body$ ? $body$, ?"), 1);
+        assert_eq!(count_placeholders("SELECT 'it''s?', ?"), 1);
+    }
+
+    #[test]
+    fn expand_params_expands_lists() {
+        let (query, params) = expand_params(
+            "SELECT * FROM t WHERE a = ? AND b IN (?) AND c = '?'".to_string(),
+            vec![
+                DatabaseValue::I64(1),
+                DatabaseValue::List(vec![DatabaseValue::I64(2), DatabaseValue::I64(3)]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            "SELECT * FROM t WHERE a = ? AND b IN (?, ?) AND c = '?'"
+        );
+        assert_eq!(params.len(), 3);
+
+        let (query, params) = expand_params(
+            "SELECT * FROM t WHERE b IN (?)".to_string(),
+            vec![DatabaseValue::List(vec![])],
+        )
+        .unwrap();
+        assert_eq!(query, "SELECT * FROM t WHERE b IN (NULL)");
+        assert!(params.is_empty());
+
+        assert!(expand_params(
+            "SELECT ?".to_string(),
+            vec![DatabaseValue::List(vec![DatabaseValue::List(vec![])])],
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn split_statements_respects_quotes_and_comments() {
+        assert_eq!(
+            split_statements("SELECT 1; SELECT ';'; -- a; b\nSELECT 2 /* ; */;"),
+            vec!["SELECT 1", "SELECT ';'", "-- a; b\nSELECT 2 /* ; */"]
+        );
+        assert_eq!(split_statements("SELECT 1;; -- done"), vec!["SELECT 1"]);
+        assert_eq!(
+            split_statements(
+                "CREATE FUNCTION f() RETURNS int AS $$ SELECT 1; $$ LANGUAGE sql; SELECT 2"
+            ),
+            vec![
+                "CREATE FUNCTION f() RETURNS int AS $$ SELECT 1; $$ LANGUAGE sql",
+                "SELECT 2"
+            ]
+        );
+    }
+
+    #[test]
+    fn split_statements_keeps_trigger_bodies() {
+        let trigger = "CREATE TRIGGER t AFTER INSERT ON a BEGIN \
+            UPDATE b SET c = CASE WHEN c > 0 THEN 1 ELSE 0 END; \
+            DELETE FROM d; \
+            END";
+        assert_eq!(
+            split_statements(&format!("{}; DROP TRIGGER t; SELECT 1", trigger)),
+            vec![trigger, "DROP TRIGGER t", "SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn number_placeholders_rewrites_qmarks() {
+        assert_eq!(
+            number_placeholders("SELECT * FROM t WHERE a = ? AND b IN (?, ?)"),
+            "SELECT * FROM t WHERE a = $1 AND b IN ($2, $3)"
+        );
+        assert_eq!(
+            number_placeholders("SELECT '?' || ?, \"a?\" FROM t"),
+            "SELECT '?' || $1, \"a?\" FROM t"
+        );
+        assert_eq!(number_placeholders("SELECT 1"), "SELECT 1");
+    }
+
+    #[test]
+    fn count_numbered_placeholders_uses_highest_number() {
+        assert_eq!(count_numbered_placeholders("SELECT $1, $2, $1"), 2);
+        assert_eq!(count_numbered_placeholders("SELECT $10"), 10);
+        assert_eq!(count_numbered_placeholders("SELECT '$3', $1"), 1);
+        assert_eq!(count_numbered_placeholders("SELECT $$body$$"), 0);
+    }
+
+    #[test]
+    fn into_postgres_binds_uuids_and_datetimes_as_text() {
+        let uuid = uuid::Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+        match DatabaseValue::Uuid(uuid).into_postgres() {
+            DatabaseValue::String(v) => assert_eq!(v, "01234567-89ab-cdef-0123-456789abcdef"),
+            v => panic!("Unexpected value {:?}", v),
+        }
+        let datetime = NaiveDate::from_ymd_opt(2021, 3, 28)
+            .and_then(|v| v.and_hms_micro_opt(1, 30, 0, 250))
+            .unwrap();
+        match DatabaseValue::DateTimeUtc(Utc.from_utc_datetime(&datetime)).into_postgres() {
+            DatabaseValue::String(v) => assert_eq!(v, "2021-03-28T01:30:00.000250+00:00"),
+            v => panic!("Unexpected value {:?}", v),
+        }
+        assert!(matches!(
+            DatabaseValue::I64(1).into_postgres(),
+            DatabaseValue::I64(1)
+        ));
+    }
+
+    #[test]
+    fn database_value_type_from_str_is_case_insensitive() {
+        assert_eq!(
+            DatabaseValueType::from_str("I64").unwrap(),
+            DatabaseValueType::I64
+        );
+        assert_eq!(
+            DatabaseValueType::from_str("naivedatetime").unwrap(),
+            DatabaseValueType::NaiveDateTime
+        );
+        for value_type in DATABASE_VALUE_TYPES {
+            let name = format!("{:?}", value_type).to_uppercase();
+            assert_eq!(DatabaseValueType::from_str(&name).unwrap(), value_type);
+        }
+        assert!(DatabaseValueType::from_str("Int").is_err());
+    }
+
+    #[test]
+    fn check_params_accepts_both_placeholder_styles() {
+        let params = vec![DatabaseValue::I64(1), DatabaseValue::I64(2)];
+        assert!(check_params("SELECT ?, ?", &params).is_ok());
+        assert!(check_params("SELECT $1, $2", &params).is_ok());
+        assert!(check_params("SELECT ?", &params).is_err());
+        assert!(check_params("SELECT $3", &params).is_err());
+    }
 }
@@ -0,0 +1,995 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use pyo3::exceptions::PyIndexError;
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyList, PyTuple};
+use rorm::row::Row;
+
+use crate::common;
+use crate::common::DatabaseValueType;
+use crate::errors;
+use crate::transaction;
+use crate::types;
+
+static DEFAULT_ARRAY_SIZE: usize = 1;
+
+/**
+Marker of a cursor which is used by a running query, the cursor is released when it's dropped
+
+The DB API's threadsafety level 2 doesn't allow sharing cursors between threads, so
+a second query while another one is still running raises ProgrammingError instead of
+overwriting the result set.
+ */
+struct InUse(Arc<AtomicBool>);
+
+impl InUse {
+    fn acquire(flag: &Arc<AtomicBool>) -> PyResult<Self> {
+        flag.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map_err(|_| {
+                errors::ProgrammingError::new_err(
+                    "Cursor is already used by another query, \
+                     cursors can't be shared between threads",
+                )
+            })?;
+        Ok(InUse(flag.clone()))
+    }
+}
+
+impl Drop for InUse {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/**
+Representation of the rows returned by the fetch methods of a cursor
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum RowFactory {
+    Tuple,
+    Dict,
+    NamedTuple,
+}
+
+impl RowFactory {
+    /**
+    Parse the name of a row factory, raising ProgrammingError for unknown names
+     */
+    pub(crate) fn from_name(name: &str) -> PyResult<Self> {
+        match name {
+            "tuple" => Ok(RowFactory::Tuple),
+            "dict" => Ok(RowFactory::Dict),
+            "namedtuple" => Ok(RowFactory::NamedTuple),
+            _ => Err(errors::ProgrammingError::new_err(format!(
+                "Unknown row factory {}, expected one of tuple, dict, namedtuple",
+                name
+            ))),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            RowFactory::Tuple => "tuple",
+            RowFactory::Dict => "dict",
+            RowFactory::NamedTuple => "namedtuple",
+        }
+    }
+}
+
+/**
+Cursor to execute queries and fetch their results as described by the DB API 2.0
+ */
+#[pyclass(module = "rorm_python")]
+pub(crate) struct Cursor {
+    pub(crate) database: Py<common::Database>,
+    pub(crate) transaction: Option<Py<transaction::Transaction>>,
+    #[pyo3(get, set)]
+    pub(crate) arraysize: usize,
+    /**
+    Number of rows in the result set of the last query, or -1 if it can't be determined
+
+    rorm doesn't report the number of affected rows, so statements without a result set
+    like a plain UPDATE report -1, as does a cursor which hasn't executed a query yet.
+    Add a RETURNING clause where supported to count the affected rows.
+     */
+    #[pyo3(get)]
+    pub(crate) rowcount: i64,
+    #[pyo3(get)]
+    pub(crate) description: Option<PyObject>,
+    /**
+    Always None, since rorm doesn't report the ID generated by the driver for raw queries
+
+    Use `INSERT ... RETURNING id` instead, which is supported by SQLite, MariaDB and
+    Postgres, and fetch the generated ID with `fetch_one` like any other result row.
+     */
+    #[pyo3(get)]
+    pub(crate) lastrowid: Option<i64>,
+    pub(crate) row_factory: RowFactory,
+    /**
+    Whether JSON columns are parsed to Python objects (the default) or returned as str
+     */
+    #[pyo3(get, set)]
+    pub(crate) parse_json: bool,
+    pub(crate) row_type: Option<PyObject>,
+    pub(crate) columns: Vec<(String, DatabaseValueType)>,
+    pub(crate) rows: Option<Vec<Row>>,
+    pub(crate) position: usize,
+    pub(crate) closed: bool,
+    pub(crate) in_use: Arc<AtomicBool>,
+}
+
+impl Cursor {
+    pub(crate) fn new(
+        database: Py<common::Database>,
+        transaction: Option<Py<transaction::Transaction>>,
+    ) -> Self {
+        Cursor {
+            database,
+            transaction,
+            arraysize: DEFAULT_ARRAY_SIZE,
+            rowcount: -1,
+            description: None,
+            lastrowid: None,
+            row_factory: RowFactory::Tuple,
+            parse_json: true,
+            row_type: None,
+            columns: vec![],
+            rows: None,
+            position: 0,
+            closed: false,
+            in_use: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /**
+    Ensure that the cursor hasn't been closed yet
+     */
+    pub(crate) fn check_open(&self) -> PyResult<()> {
+        if self.closed {
+            return Err(errors::InterfaceError::new_err(
+                "Cursor has already been closed",
+            ));
+        }
+        Ok(())
+    }
+
+    /**
+    Retrieve the executor of queries, which targets the transaction if the cursor has one
+     */
+    pub(crate) fn executor(&self, py: Python) -> PyResult<common::Executor> {
+        let database = self.database.borrow(py);
+        let target = match &self.transaction {
+            Some(transaction) => common::Target::Transaction(transaction.borrow(py).sender()?),
+            None if !database.autocommit => {
+                database.db()?;
+                common::Target::Implicit(self.database.clone_ref(py))
+            }
+            None => common::Target::Pool(database.db()?),
+        };
+        Ok(common::Executor {
+            target,
+            backend: database.backend,
+            logger: database.logger(py),
+            timeout: None,
+            slow_query_threshold: database.slow_query_threshold,
+        })
+    }
+
+    /**
+    Retrieve the buffered result set of the last executed query
+     */
+    pub(crate) fn result_rows(&self) -> PyResult<&Vec<Row>> {
+        self.check_open()?;
+        if self.in_use.load(Ordering::Acquire) {
+            return Err(errors::ProgrammingError::new_err(
+                "Cursor is still executing a query, cursors can't be shared between threads",
+            ));
+        }
+        match &self.rows {
+            Some(rows) => Ok(rows),
+            None => Err(errors::ProgrammingError::new_err(
+                "No query has been executed yet",
+            )),
+        }
+    }
+
+    /**
+    Build the namedtuple type of the current result set once, if the row factory requires it
+
+    Column names which aren't valid Python identifiers are renamed to their position.
+     */
+    pub(crate) fn prepare_row_type(&mut self, py: Python) -> PyResult<()> {
+        if self.row_factory != RowFactory::NamedTuple || self.row_type.is_some() {
+            return Ok(());
+        }
+        let names: Vec<&str> = self.columns.iter().map(|(name, _)| name.as_str()).collect();
+        let kwargs = [("rename", true)].into_py_dict(py);
+        let row_type = py
+            .import("collections")?
+            .getattr("namedtuple")?
+            .call(("Row", names), Some(kwargs))?;
+        self.row_type = Some(row_type.into_py(py));
+        Ok(())
+    }
+
+    /**
+    Fetch up to `size` rows of the result set, starting at the current position
+     */
+    pub(crate) fn take_rows(&mut self, py: Python, size: usize) -> PyResult<Vec<PyObject>> {
+        self.prepare_row_type(py)?;
+        let rows = self.result_rows()?;
+        let end = rows.len().min(self.position.saturating_add(size));
+        let result = rows[self.position..end]
+            .iter()
+            .map(|row| self.convert(py, row))
+            .collect::<PyResult<Vec<PyObject>>>()?;
+        self.position = end;
+        Ok(result)
+    }
+
+    /**
+    Prepare the execution of a query, the returned future stores the result set on the cursor
+     */
+    fn run(
+        slf: PyRef<Self>,
+        py: Python,
+        query: String,
+        params: Option<Vec<common::DatabaseValue>>,
+        columns: Option<Vec<common::ColumnSpec>>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<impl Future<Output = PyResult<i64>> + Send + 'static> {
+        slf.check_open()?;
+        let params = params.unwrap_or_default();
+        common::check_params(&query, &params)?;
+        let mut executor = slf.executor(py)?;
+        executor.timeout = timeout_ms.map(Duration::from_millis);
+        let in_use = InUse::acquire(&slf.in_use)?;
+        let cursor: Py<Cursor> = slf.into();
+        Ok(async move {
+            let _in_use = in_use;
+            let rows = executor.raw_sql(query, params).await?;
+            let rowcount = if rows.is_empty() {
+                -1
+            } else {
+                rows.len() as i64
+            };
+            let columns = columns.unwrap_or_default();
+            common::ColumnSpec::check_complete(columns.len(), &rows)?;
+            let columns = common::ColumnSpec::resolve(columns, &rows)?;
+            Python::with_gil(|py| {
+                let mut cursor = cursor.borrow_mut(py);
+                cursor.rowcount = rowcount;
+                cursor.description = Cursor::describe(py, &columns)?;
+                cursor.lastrowid = None;
+                cursor.row_type = None;
+                cursor.columns = columns;
+                cursor.rows = Some(rows);
+                cursor.position = 0;
+                Ok::<_, PyErr>(())
+            })?;
+            Ok(rowcount)
+        })
+    }
+
+    /**
+    Prepare the execution of a query for each of the parameter sets
+     */
+    fn run_many(
+        slf: PyRef<Self>,
+        py: Python,
+        query: String,
+        seq_of_params: Vec<Vec<common::DatabaseValue>>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<impl Future<Output = PyResult<i64>> + Send + 'static> {
+        slf.check_open()?;
+        for params in &seq_of_params {
+            common::check_params(&query, params)?;
+        }
+        let mut executor = slf.executor(py)?;
+        executor.timeout = timeout_ms.map(Duration::from_millis);
+        let in_use = InUse::acquire(&slf.in_use)?;
+        let cursor: Py<Cursor> = slf.into();
+        Ok(async move {
+            let _in_use = in_use;
+            let mut rowcount = -1;
+            for (index, params) in seq_of_params.into_iter().enumerate() {
+                match executor.raw_sql(query.clone(), params).await {
+                    Ok(rows) if rows.is_empty() => {}
+                    Ok(rows) => rowcount = rowcount.max(0) + rows.len() as i64,
+                    Err(err) => {
+                        return Err(errors::with_context(
+                            err,
+                            &format!("Failed to execute parameter set {}", index),
+                        ))
+                    }
+                }
+            }
+            Python::with_gil(|py| {
+                let mut cursor = cursor.borrow_mut(py);
+                cursor.rowcount = rowcount;
+                cursor.description = None;
+                cursor.lastrowid = None;
+                cursor.row_type = None;
+                cursor.columns = vec![];
+                cursor.rows = None;
+                cursor.position = 0;
+            });
+            Ok(rowcount)
+        })
+    }
+
+    /**
+    Build the DB API description of the given columns as list of 7-tuples
+
+    Only the name and the type code of the columns are known, all other fields are None.
+     */
+    pub(crate) fn describe(
+        py: Python,
+        columns: &[(String, DatabaseValueType)],
+    ) -> PyResult<Option<PyObject>> {
+        if columns.is_empty() {
+            return Ok(None);
+        }
+        let mut description = Vec::with_capacity(columns.len());
+        for (name, col_t) in columns {
+            description.push(
+                (
+                    name.as_str(),
+                    col_t.type_code(py)?,
+                    py.None(),
+                    py.None(),
+                    py.None(),
+                    py.None(),
+                    py.None(),
+                )
+                    .into_py(py),
+            );
+        }
+        Ok(Some(PyList::new(py, description).into_py(py)))
+    }
+
+    /**
+    Convert a single row of the result set according to the row factory of the cursor
+     */
+    fn convert(&self, py: Python, row: &Row) -> PyResult<PyObject> {
+        let backend = self.database.borrow(py).backend;
+        match self.row_factory {
+            RowFactory::Tuple | RowFactory::NamedTuple => {
+                let mut values = Vec::with_capacity(self.columns.len());
+                for (col, col_t) in &self.columns {
+                    values.push(convert_value!(
+                        py,
+                        row,
+                        backend,
+                        col.as_str(),
+                        col_t,
+                        self.parse_json
+                    ));
+                }
+                let values = PyTuple::new(py, values);
+                match &self.row_type {
+                    Some(row_type) if self.row_factory == RowFactory::NamedTuple => {
+                        Ok(row_type.call1(py, values)?)
+                    }
+                    _ => Ok(values.into_py(py)),
+                }
+            }
+            RowFactory::Dict => {
+                let columns: Vec<(&str, DatabaseValueType)> = self
+                    .columns
+                    .iter()
+                    .map(|(name, col_t)| (name.as_str(), *col_t))
+                    .collect();
+                Ok(convert_row!(py, row, backend, columns, self.parse_json).into_py(py))
+            }
+        }
+    }
+}
+
+#[pymethods]
+impl Cursor {
+    /**
+    Representation of the fetched rows, either "tuple" (the default), "namedtuple" or "dict"
+     */
+    #[getter]
+    fn row_factory(&self) -> &'static str {
+        self.row_factory.name()
+    }
+
+    #[setter]
+    fn set_row_factory(&mut self, value: &str) -> PyResult<()> {
+        self.row_factory = RowFactory::from_name(value)?;
+        self.row_type = None;
+        Ok(())
+    }
+
+    /**
+    Close the cursor, every further operation on it will raise an InterfaceError
+
+    Closing an already closed cursor does nothing. The buffered result set is released.
+     */
+    fn close(&mut self) {
+        self.closed = true;
+        self.rows = None;
+        self.row_type = None;
+    }
+
+    /**
+    Enter the cursor as context manager, use it as `with db.cursor() as cursor:`
+     */
+    fn __enter__(slf: PyRef<Self>) -> PyResult<PyRef<Self>> {
+        slf.check_open()?;
+        Ok(slf)
+    }
+
+    /**
+    Close the cursor when leaving the context, regardless of any propagating exception
+     */
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) {
+        self.close();
+    }
+
+    /**
+    Enter the cursor as async context manager, use it as `async with db.cursor() as cursor:`
+     */
+    fn __aenter__<'p>(slf: PyRef<'p, Self>, py: Python<'p>) -> PyResult<&'p PyAny> {
+        slf.check_open()?;
+        let cursor: Py<Cursor> = slf.into();
+        pyo3_asyncio::tokio::future_into_py(py, async move { Ok(cursor) })
+    }
+
+    /**
+    Close the cursor when leaving the async context, regardless of any propagating exception
+     */
+    fn __aexit__<'p>(
+        &mut self,
+        py: Python<'p>,
+        _exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> PyResult<&'p PyAny> {
+        self.close();
+        pyo3_asyncio::tokio::future_into_py(py, async move { Ok(()) })
+    }
+
+    /**
+    Execute a raw SQL query with optional positional parameters for its qmark placeholders
+
+    The columns of the result set have to be given as list of names, since rorm rows don't
+    expose them, and are used to decode the rows when fetching them afterwards. A query
+    returning rows without columns raises ProgrammingError. The type of a column given only
+    by name is inferred from its values, a (name, DatabaseValueType) tuple forces one.
+    The returned awaitable resolves to the `rowcount`, i.e. the number of rows the query
+    returned or -1 for statements without a result set, since rorm doesn't report the number
+    of affected rows for raw queries.
+    A query taking longer than the optional `timeout_ms` raises an OperationalError.
+     */
+    fn execute<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        query: String,
+        params: Option<Vec<common::DatabaseValue>>,
+        columns: Option<Vec<common::ColumnSpec>>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<&'p PyAny> {
+        let future = Cursor::run(slf, py, query, params, columns, timeout_ms)?;
+        pyo3_asyncio::tokio::future_into_py(py, future)
+    }
+
+    /**
+    Execute a raw SQL query like `execute` and fetch all of its rows in the same step
+
+    The returned awaitable resolves to a Result with the `rows`, converted like by `fetch_all`,
+    and the `rowcount`. This is mostly useful for `INSERT ... RETURNING` style statements.
+     */
+    fn execute_fetch_all<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        query: String,
+        params: Option<Vec<common::DatabaseValue>>,
+        columns: Option<Vec<common::ColumnSpec>>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<&'p PyAny> {
+        let cursor: Py<Cursor> = slf.into();
+        let future = Cursor::run(cursor.borrow(py), py, query, params, columns, timeout_ms)?;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let rowcount = future.await?;
+            Python::with_gil(|py| {
+                let rows = cursor.borrow_mut(py).take_rows(py, usize::MAX)?;
+                Ok(QueryResult { rows, rowcount })
+            })
+        })
+    }
+
+    /**
+    Execute a raw SQL query like `execute`, but block until it has finished
+
+    Returns the `rowcount`, which is -1 for statements without a result set.
+     */
+    fn execute_blocking(
+        slf: PyRef<Self>,
+        py: Python,
+        query: String,
+        params: Option<Vec<common::DatabaseValue>>,
+        columns: Option<Vec<common::ColumnSpec>>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<i64> {
+        let future = Cursor::run(slf, py, query, params, columns, timeout_ms)?;
+        common::block_on(py, future)
+    }
+
+    /**
+    Execute the same raw SQL query once for each of the given parameter sets
+
+    The rowcount accumulates the rows returned by all executions, or is -1 if none of them
+    returned rows, since rorm doesn't report the number of affected rows. The queries aren't
+    wrapped in a transaction, so the parameter sets executed before a failing one will persist.
+    A failure raises the original exception class with the index of the parameter set
+    prepended to its message.
+     */
+    fn executemany<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        query: String,
+        seq_of_params: Vec<Vec<common::DatabaseValue>>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<&'p PyAny> {
+        let future = Cursor::run_many(slf, py, query, seq_of_params, timeout_ms)?;
+        pyo3_asyncio::tokio::future_into_py(py, future)
+    }
+
+    /**
+    Execute the query for each parameter set like `executemany`, but block until all have finished
+     */
+    fn executemany_blocking(
+        slf: PyRef<Self>,
+        py: Python,
+        query: String,
+        seq_of_params: Vec<Vec<common::DatabaseValue>>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<i64> {
+        let future = Cursor::run_many(slf, py, query, seq_of_params, timeout_ms)?;
+        common::block_on(py, future)
+    }
+
+    /**
+    Call a stored procedure with the given parameters, the returned awaitable resolves to them
+
+    The parameters are returned unchanged, since rorm can't bind OUT parameters. On Postgres,
+    the values of OUT and INOUT parameters are returned as result set of the CALL statement
+    and can be fetched afterwards by passing their names as `columns`. SQLite doesn't support
+    stored procedures and raises NotSupportedError.
+     */
+    fn callproc<'p>(
+        slf: PyRef<'p, Self>,
+        py: Python<'p>,
+        procname: String,
+        params: Option<Vec<common::DatabaseValue>>,
+        columns: Option<Vec<common::ColumnSpec>>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<&'p PyAny> {
+        if slf.database.borrow(py).backend == common::Backend::SQLite {
+            return Err(errors::NotSupportedError::new_err(
+                "SQLite doesn't support stored procedures",
+            ));
+        }
+        if procname.is_empty()
+            || !procname
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        {
+            return Err(errors::ProgrammingError::new_err(format!(
+                "Invalid procedure name {}",
+                procname
+            )));
+        }
+        let params = params.unwrap_or_default();
+        let placeholders = vec!["?"; params.len()].join(", ");
+        let query = format!("CALL {}({})", procname, placeholders);
+        let future = Cursor::run(slf, py, query, Some(params.clone()), columns, timeout_ms)?;
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            future.await?;
+            Python::with_gil(|py| {
+                params
+                    .iter()
+                    .map(|v| v.to_py(py))
+                    .collect::<PyResult<Vec<PyObject>>>()
+            })
+        })
+    }
+
+    /**
+    Fetch the next row of the result set or None when no rows are left
+     */
+    fn fetch_one(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        self.prepare_row_type(py)?;
+        let row = match self.result_rows()?.get(self.position) {
+            Some(row) => self.convert(py, row)?,
+            None => return Ok(None),
+        };
+        self.position += 1;
+        Ok(Some(row))
+    }
+
+    /**
+    Fetch the first column of the next row, e.g. for `SELECT count(*)`, or None if no rows are left
+
+    Only the first column is decoded, regardless of the row factory.
+     */
+    fn fetch_scalar(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let (col, col_t) = match self.columns.first() {
+            Some(column) => column,
+            None => {
+                self.result_rows()?;
+                return Err(errors::ProgrammingError::new_err(
+                    "The query has no columns to fetch",
+                ));
+            }
+        };
+        let value = match self.result_rows()?.get(self.position) {
+            Some(row) => {
+                let backend = self.database.borrow(py).backend;
+                convert_value!(py, row, backend, col.as_str(), col_t, self.parse_json)
+            }
+            None => return Ok(None),
+        };
+        self.position += 1;
+        Ok(Some(value))
+    }
+
+    /**
+    Retrieve the keys generated by the last `INSERT ... RETURNING` as dict of column name -> value
+
+    Unlike `lastrowid`, this supports composite and non-integer keys. The keys are the columns
+    of the first row of the result set, regardless of the row factory and without consuming it.
+    Resolves to None if the insert didn't return a row. RETURNING is supported by SQLite,
+    MariaDB and Postgres, so the MySQL driver raises NotSupportedError for an insert without
+    returned rows, since MySQL itself doesn't support RETURNING.
+     */
+    fn inserted_keys(&self, py: Python) -> PyResult<Option<PyObject>> {
+        let row = match self.result_rows()?.first() {
+            Some(row) => row,
+            None if self.database.borrow(py).backend == common::Backend::MySQL => {
+                return Err(errors::NotSupportedError::new_err(
+                    "The insert returned no keys, MySQL doesn't support RETURNING, only MariaDB \
+                     does. Query LAST_INSERT_ID() for auto-increment keys instead",
+                ))
+            }
+            None => return Ok(None),
+        };
+        if self.columns.is_empty() {
+            return Err(errors::ProgrammingError::new_err(
+                "The query has no columns, specify the key columns of the RETURNING clause",
+            ));
+        }
+        let backend = self.database.borrow(py).backend;
+        let columns: Vec<(&str, DatabaseValueType)> = self
+            .columns
+            .iter()
+            .map(|(name, col_t)| (name.as_str(), *col_t))
+            .collect();
+        Ok(Some(
+            convert_row!(py, row, backend, columns, self.parse_json).into_py(py),
+        ))
+    }
+
+    /**
+    Fetch the next `size` rows of the result set as list
+
+    The size defaults to the cursor's arraysize. Fewer rows are returned
+    when the result set is exhausted, which is signaled by an empty list.
+     */
+    fn fetch_many(&mut self, py: Python, size: Option<usize>) -> PyResult<Vec<PyObject>> {
+        let size = size.unwrap_or(self.arraysize);
+        self.take_rows(py, size)
+    }
+
+    /**
+    Fetch all remaining rows of the result set as list
+     */
+    fn fetch_all(&mut self, py: Python) -> PyResult<Vec<PyObject>> {
+        self.take_rows(py, usize::MAX)
+    }
+
+    /**
+    Fetch all remaining rows of the result set as pandas DataFrame
+
+    The values are collected per column, so every column is converted to a pandas Series
+    at once. Integer and boolean columns use the nullable pandas dtypes, where NULL becomes
+    `pandas.NA`; NULL values of float columns become NaN. Requires the `pandas` feature.
+     */
+    #[cfg(feature = "pandas")]
+    fn fetch_df(&mut self, py: Python) -> PyResult<PyObject> {
+        let pandas = py.import("pandas")?;
+        let backend = self.database.borrow(py).backend;
+        let rows = self.result_rows()?;
+        let end = rows.len();
+        let rows = &rows[self.position..];
+        let data = pyo3::types::PyDict::new(py);
+        for (col, col_t) in &self.columns {
+            let mut values = Vec::with_capacity(rows.len());
+            for row in rows {
+                values.push(convert_value!(
+                    py,
+                    row,
+                    backend,
+                    col.as_str(),
+                    col_t,
+                    self.parse_json
+                ));
+            }
+            let dtype = match col_t {
+                DatabaseValueType::I64 | DatabaseValueType::I32 | DatabaseValueType::I16 => {
+                    Some("Int64")
+                }
+                DatabaseValueType::U64 | DatabaseValueType::U32 => Some("UInt64"),
+                DatabaseValueType::Bool => Some("boolean"),
+                DatabaseValueType::F64 | DatabaseValueType::F32 => Some("float64"),
+                _ => None,
+            };
+            let kwargs = pyo3::types::PyDict::new(py);
+            if let Some(dtype) = dtype {
+                kwargs.set_item("dtype", dtype)?;
+            }
+            data.set_item(
+                col,
+                pandas.getattr("Series")?.call((values,), Some(kwargs))?,
+            )?;
+        }
+        self.position = end;
+        Ok(pandas.getattr("DataFrame")?.call1((data,))?.into_py(py))
+    }
+
+    /**
+    Fetch all remaining rows of the result set as PyArrow RecordBatch
+
+    The Arrow arrays are built directly from the rows without creating Python objects
+    for the single values. Requires the `arrow` feature.
+     */
+    #[cfg(feature = "arrow")]
+    fn fetch_arrow(&mut self, py: Python) -> PyResult<PyObject> {
+        let rows = self.result_rows()?;
+        let backend = self.database.borrow(py).backend;
+        let batch =
+            crate::record_batch::to_pyarrow(py, backend, &self.columns, &rows[self.position..])?;
+        self.position = rows.len();
+        Ok(batch)
+    }
+
+    /**
+    Predefine the sizes of the parameters of the next query, which is accepted but ignored
+
+    Every item has to be None, a non-negative size or a type object.
+     */
+    fn setinputsizes(&self, sizes: &PyAny) -> PyResult<()> {
+        self.check_open()?;
+        let sizes: Vec<&PyAny> = sizes.extract().map_err(|_| {
+            errors::ProgrammingError::new_err("The input sizes have to be a sequence")
+        })?;
+        for size in sizes {
+            let valid = size.is_none()
+                || size.extract::<usize>().is_ok()
+                || size.extract::<DatabaseValueType>().is_ok()
+                || size.extract::<PyRef<types::DBAPITypeObject>>().is_ok();
+            if !valid {
+                return Err(errors::ProgrammingError::new_err(format!(
+                    "Invalid input size {}, expected None, a size or a type object",
+                    size
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /**
+    Predefine the buffer size of large columns for fetching, which is accepted but ignored
+
+    The column is given by its index or name and defaults to all large columns.
+     */
+    fn setoutputsizes(&self, size: &PyAny, column: Option<&PyAny>) -> PyResult<()> {
+        self.check_open()?;
+        if size.extract::<usize>().is_err() {
+            return Err(errors::ProgrammingError::new_err(format!(
+                "Invalid output size {}, expected a non-negative integer",
+                size
+            )));
+        }
+        if let Some(column) = column {
+            if column.extract::<usize>().is_err() && column.extract::<&str>().is_err() {
+                return Err(errors::ProgrammingError::new_err(format!(
+                    "Invalid column {}, expected its index or name",
+                    column
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /**
+    Skip to the next result set, which always returns None as there is only one
+
+    rorm returns the rows of all result sets of a query as a single list, so additional
+    result sets, e.g. of MySQL procedures, are part of the current result set.
+     */
+    fn nextset(&self) -> PyResult<Option<bool>> {
+        self.result_rows()?;
+        Ok(None)
+    }
+
+    /**
+    Move the position within the result set, either relative to the current one or absolute
+
+    The position may be placed directly after the last row, which exhausts the result set.
+    Positions outside of the result set raise an IndexError and keep the current position.
+     */
+    fn scroll(&mut self, value: i64, mode: Option<&str>) -> PyResult<()> {
+        let len = self.result_rows()?.len() as i64;
+        let position = match mode.unwrap_or("relative") {
+            "relative" => self.position as i64 + value,
+            "absolute" => value,
+            mode => {
+                return Err(errors::ProgrammingError::new_err(format!(
+                    "Unknown scroll mode {}, expected one of relative, absolute",
+                    mode
+                )))
+            }
+        };
+        if position < 0 || position > len {
+            return Err(PyIndexError::new_err(format!(
+                "Scroll position {} is out of range of the {} rows",
+                position, len
+            )));
+        }
+        self.position = position as usize;
+        Ok(())
+    }
+
+    /**
+    Iterate over the remaining rows of the result set like `fetch_one` until it's exhausted
+     */
+    fn __iter__(slf: PyRef<Self>) -> PyResult<PyRef<Self>> {
+        slf.result_rows()?;
+        Ok(slf)
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        self.fetch_one(py)
+    }
+
+    /**
+    Iterate asynchronously over the remaining rows, use it as `async for row in cursor:`
+     */
+    fn __aiter__(slf: PyRef<Self>) -> PyResult<PyRef<Self>> {
+        slf.result_rows()?;
+        Ok(slf)
+    }
+
+    fn __anext__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let row = match self.fetch_one(py)? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let awaitable = pyo3_asyncio::tokio::future_into_py(py, async move { Ok(row) })?;
+        Ok(Some(awaitable.into_py(py)))
+    }
+
+    /**
+    Iterate asynchronously over the remaining rows of the already buffered result set
+
+    This doesn't stream from the database: rorm only offers a streaming API for its query
+    builder, not for raw SQL, so `execute` has already loaded the complete result set into
+    memory. Only the conversion to Python objects happens one row at a time, which avoids
+    building the complete list of `fetch_all`. Page through large tables with LIMIT instead.
+     */
+    fn stream(slf: PyRef<Self>) -> PyResult<RowStream> {
+        slf.result_rows()?;
+        Ok(RowStream { cursor: slf.into() })
+    }
+
+    /**
+    Iterate over the remaining rows of the result set in lists of up to `chunk_size` rows
+
+    The last chunk may be shorter. Both `for` and `async for` are supported. Like `stream`,
+    only the conversion to Python objects happens per chunk, the rows are buffered by `execute`.
+     */
+    fn fetch_chunks(slf: PyRef<Self>, chunk_size: usize) -> PyResult<ChunkStream> {
+        slf.result_rows()?;
+        if chunk_size == 0 {
+            return Err(errors::ProgrammingError::new_err(
+                "The chunk size must be at least 1",
+            ));
+        }
+        Ok(ChunkStream {
+            cursor: slf.into(),
+            chunk_size,
+        })
+    }
+}
+
+/**
+Asynchronous iterator over the buffered rows of a cursor, which is returned by `Cursor.stream`
+ */
+#[pyclass(module = "rorm_python")]
+pub(crate) struct RowStream {
+    cursor: Py<Cursor>,
+}
+
+#[pymethods]
+impl RowStream {
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__(&self, py: Python) -> PyResult<Option<PyObject>> {
+        self.cursor.borrow_mut(py).__anext__(py)
+    }
+}
+
+/**
+Rows and rowcount of a query, which is returned by `Cursor.execute_fetch_all`
+ */
+#[pyclass(name = "Result", module = "rorm_python")]
+pub(crate) struct QueryResult {
+    #[pyo3(get)]
+    rows: Vec<PyObject>,
+    #[pyo3(get)]
+    rowcount: i64,
+}
+
+#[pymethods]
+impl QueryResult {
+    fn __len__(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+/**
+Iterator over the rows of a cursor in chunks, which is returned by `Cursor.fetch_chunks`
+ */
+#[pyclass(module = "rorm_python")]
+pub(crate) struct ChunkStream {
+    cursor: Py<Cursor>,
+    chunk_size: usize,
+}
+
+impl ChunkStream {
+    /**
+    Fetch the next chunk of rows, which is None instead of empty when the rows are exhausted
+     */
+    fn next_chunk(&self, py: Python) -> PyResult<Option<Vec<PyObject>>> {
+        let chunk = self.cursor.borrow_mut(py).take_rows(py, self.chunk_size)?;
+        Ok(if chunk.is_empty() { None } else { Some(chunk) })
+    }
+}
+
+#[pymethods]
+impl ChunkStream {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python) -> PyResult<Option<Vec<PyObject>>> {
+        self.next_chunk(py)
+    }
+
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__(&self, py: Python) -> PyResult<Option<PyObject>> {
+        let chunk = match self.next_chunk(py)? {
+            Some(chunk) => chunk,
+            None => return Ok(None),
+        };
+        let awaitable = pyo3_asyncio::tokio::future_into_py(py, async move { Ok(chunk) })?;
+        Ok(Some(awaitable.into_py(py)))
+    }
+}
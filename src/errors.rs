@@ -1,5 +1,7 @@
 use pyo3::create_exception;
-use pyo3::exceptions::PyException;
+use pyo3::exceptions::{PyException, PyWarning};
+use pyo3::prelude::*;
+use pyo3::types::PyType;
 
 create_exception!(
     rorm_python,
@@ -8,10 +10,12 @@ create_exception!(
     "Exception for errors originating from the Rust ORM"
 );
 
+// Python's Warning is a subclass of Exception as required by the DB API, and it is
+// required as base to use this class as category of warnings.warn and the warning filters
 create_exception!(
     rorm_python,
     Warning,
-    PyException,
+    PyWarning,
     "Exception raised for important warnings like data truncations while inserting"
 );
 
@@ -70,3 +74,128 @@ create_exception!(
     DatabaseError,
     "Exception raised in case a method or database API was used which is not supported by the database, e.g. requesting a .rollback() on a connection that does not support transaction or has transactions turned off"
 );
+
+/**
+Find the exception class matching an error code reported by the database
+
+Codes with five characters are SQLSTATEs as used by Postgres and MySQL,
+all other codes are (extended) SQLite result codes.
+ */
+fn classify_error_code<'p>(py: Python<'p>, code: &str) -> &'p PyType {
+    if code.len() == 5 {
+        return match &code[..2] {
+            "23" => py.get_type::<IntegrityError>(),
+            "22" => py.get_type::<DataError>(),
+            "42" | "3D" | "3F" => py.get_type::<ProgrammingError>(),
+            "08" | "40" | "53" | "57" | "58" | "HY" => py.get_type::<OperationalError>(),
+            "0A" => py.get_type::<NotSupportedError>(),
+            "XX" => py.get_type::<InternalError>(),
+            _ => py.get_type::<DatabaseError>(),
+        };
+    }
+    // The primary result code is stored in the lower 8 bits of extended SQLite result codes
+    match code.parse::<i32>().map(|v| v & 0xff) {
+        Ok(19) => py.get_type::<IntegrityError>(),
+        Ok(18) | Ok(20) | Ok(25) => py.get_type::<DataError>(),
+        Ok(1) => py.get_type::<ProgrammingError>(),
+        Ok(5) | Ok(6) | Ok(8) | Ok(10) | Ok(13) | Ok(14) => py.get_type::<OperationalError>(),
+        Ok(2) | Ok(11) | Ok(26) => py.get_type::<InternalError>(),
+        _ => py.get_type::<DatabaseError>(),
+    }
+}
+
+/**
+Find the exception class matching an error returned by rorm
+ */
+fn classify_rorm_error<'p>(py: Python<'p>, err: &rorm::error::Error) -> &'p PyType {
+    match err {
+        rorm::error::Error::ConfigurationError(_) => py.get_type::<InterfaceError>(),
+        rorm::error::Error::DecodeError(_) => py.get_type::<DataError>(),
+        rorm::error::Error::SqlxError(err) => match err {
+            sqlx::Error::Database(db_err) => match db_err.code() {
+                Some(code) => classify_error_code(py, &code),
+                None => py.get_type::<DatabaseError>(),
+            },
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::Protocol(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => py.get_type::<OperationalError>(),
+            sqlx::Error::Configuration(_) => py.get_type::<InterfaceError>(),
+            sqlx::Error::ColumnNotFound(_) | sqlx::Error::ColumnIndexOutOfBounds { .. } => {
+                py.get_type::<ProgrammingError>()
+            }
+            sqlx::Error::ColumnDecode { .. } | sqlx::Error::Decode(_) => py.get_type::<DataError>(),
+            _ => py.get_type::<DatabaseError>(),
+        },
+    }
+}
+
+/**
+Retrieve the SQLSTATE of an error returned by rorm, if the database reported one
+ */
+fn sqlstate(err: &rorm::error::Error) -> Option<String> {
+    match err {
+        rorm::error::Error::SqlxError(sqlx::Error::Database(db_err)) => db_err
+            .code()
+            .filter(|code| code.len() == 5)
+            .map(|code| code.to_string()),
+        _ => None,
+    }
+}
+
+/**
+Convert an error returned by rorm into the matching Python exception
+
+The exception's only arg is its message, the SQLSTATE (or None) is available
+as `sqlstate` attribute. The debug representation of the original error is
+attached as cause of the exception.
+ */
+pub(crate) fn map_rorm_error(err: rorm::error::Error) -> PyErr {
+    let message = err.to_string();
+    let sqlstate = sqlstate(&err);
+    Python::with_gil(|py| {
+        let exc = PyErr::from_type(classify_rorm_error(py, &err), (message,));
+        exc.set_cause(py, Some(Error::new_err(format!("{:?}", err))));
+        if let Err(attr_err) = exc.value(py).setattr("sqlstate", sqlstate) {
+            return attr_err;
+        }
+        exc
+    })
+}
+
+/**
+Prefix the message of an exception with some context, keeping its class and SQLSTATE
+
+The message is taken from the first arg of the exception, falling back to its str for
+exceptions without args. The original exception is attached as cause.
+ */
+pub(crate) fn with_context(err: PyErr, context: &str) -> PyErr {
+    Python::with_gil(|py| {
+        let value = err.value(py);
+        let original = match value.getattr("args").and_then(|args| args.get_item(0)) {
+            Ok(arg) => arg.str().map(|v| v.to_string_lossy().into_owned()),
+            Err(_) => value.str().map(|v| v.to_string_lossy().into_owned()),
+        };
+        let original = match original {
+            Ok(original) => original,
+            Err(str_err) => return str_err,
+        };
+        let exc = PyErr::from_type(err.get_type(py), (format!("{}: {}", context, original),));
+        if let Ok(sqlstate) = value.getattr("sqlstate") {
+            if let Err(attr_err) = exc.value(py).setattr("sqlstate", sqlstate) {
+                return attr_err;
+            }
+        }
+        exc.set_cause(py, Some(err));
+        exc
+    })
+}
+
+/**
+Convert an error returned by sqlx directly, e.g. by the Postgres listener, like `map_rorm_error`
+ */
+pub(crate) fn map_sqlx_error(err: sqlx::Error) -> PyErr {
+    map_rorm_error(rorm::error::Error::SqlxError(err))
+}
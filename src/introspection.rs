@@ -0,0 +1,375 @@
+use pyo3::prelude::*;
+
+use rorm::row::Row;
+
+use crate::common::{Backend, DatabaseValue, DatabaseValueType};
+use crate::errors;
+
+/**
+Query which retrieves the version string of the server as column `version`
+ */
+pub(crate) fn server_version_query(backend: Backend) -> &'static str {
+    match backend {
+        Backend::SQLite => "SELECT sqlite_version() AS version",
+        Backend::MySQL | Backend::Postgres => "SELECT version() AS version",
+    }
+}
+
+/**
+Parse the leading number of a version component like `5` in `5-MariaDB` or `16` in `16beta1`
+ */
+fn leading_number(component: &str) -> Option<u32> {
+    let end = component
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(component.len());
+    component[..end].parse().ok()
+}
+
+/**
+Parse the version string reported by the server into `(major, minor, patch)`
+
+Postgres reports e.g. `PostgreSQL 14.5 (Debian 14.5-1) on x86_64-pc-linux-gnu, ...`, MySQL
+`8.0.30` or `10.6.5-MariaDB-1:10.6.5+maria~focal` and SQLite `3.39.2`. Missing components,
+like the patch level of Postgres versions, are 0.
+ */
+pub(crate) fn parse_server_version(backend: Backend, raw: &str) -> PyResult<(u32, u32, u32)> {
+    let mut words = raw.split_whitespace();
+    let word = match backend {
+        Backend::Postgres => words.find(|v| v.starts_with(|c: char| c.is_ascii_digit())),
+        Backend::SQLite | Backend::MySQL => words.next(),
+    };
+    let mut components = word.unwrap_or("").split('.').map(leading_number);
+    match components.next().flatten() {
+        Some(major) => Ok((
+            major,
+            components.next().flatten().unwrap_or(0),
+            components.next().flatten().unwrap_or(0),
+        )),
+        None => Err(errors::InterfaceError::new_err(format!(
+            "Unexpected server version format: {}",
+            raw
+        ))),
+    }
+}
+
+/**
+Query which lists the tables as column `name`, optionally filtered by the schema
+
+The schema defaults to the current schema on Postgres and the current database on MySQL.
+SQLite has no schemas and raises NotSupportedError for a schema filter.
+ */
+pub(crate) fn list_tables_query(
+    backend: Backend,
+    schema: Option<String>,
+) -> PyResult<(String, Vec<DatabaseValue>)> {
+    let (query, params) = match (backend, schema) {
+        (Backend::SQLite, Some(_)) => {
+            return Err(errors::NotSupportedError::new_err(
+                "SQLite doesn't support filtering tables by schema",
+            ))
+        }
+        (Backend::SQLite, None) => (
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            vec![],
+        ),
+        (Backend::MySQL, Some(schema)) => (
+            "SELECT table_name AS name FROM information_schema.tables \
+            WHERE table_type = 'BASE TABLE' AND table_schema = ?",
+            vec![DatabaseValue::String(schema)],
+        ),
+        (Backend::MySQL, None) => (
+            "SELECT table_name AS name FROM information_schema.tables \
+            WHERE table_type = 'BASE TABLE' AND table_schema = DATABASE()",
+            vec![],
+        ),
+        (Backend::Postgres, Some(schema)) => (
+            "SELECT tablename::text AS name FROM pg_catalog.pg_tables WHERE schemaname = ?",
+            vec![DatabaseValue::String(schema)],
+        ),
+        (Backend::Postgres, None) => (
+            "SELECT tablename::text AS name FROM pg_catalog.pg_tables \
+            WHERE schemaname = current_schema()",
+            vec![],
+        ),
+    };
+    Ok((query.to_string(), params))
+}
+
+/**
+Collect the sorted table names from the rows of the `list_tables_query`
+ */
+pub(crate) fn table_names(rows: &[Row]) -> PyResult<Vec<String>> {
+    let mut names = rows
+        .iter()
+        .map(|row| {
+            row.get::<&str, &str>("name")
+                .map(|v| v.to_string())
+                .map_err(errors::map_rorm_error)
+        })
+        .collect::<PyResult<Vec<String>>>()?;
+    names.sort();
+    Ok(names)
+}
+
+/**
+Query which describes the columns of a table as `name`, `type`, `nullable` and `dflt`
+
+The columns are ordered by their position in the table.
+ */
+pub(crate) fn describe_table_query(backend: Backend) -> &'static str {
+    match backend {
+        Backend::SQLite => {
+            "SELECT name, type, \"notnull\" = 0 AS nullable, dflt_value AS dflt \
+            FROM pragma_table_info(?) ORDER BY cid"
+        }
+        Backend::MySQL => {
+            "SELECT column_name AS name, column_type AS type, is_nullable = 'YES' AS nullable, \
+            column_default AS dflt FROM information_schema.columns \
+            WHERE table_schema = DATABASE() AND table_name = ? ORDER BY ordinal_position"
+        }
+        Backend::Postgres => {
+            "SELECT column_name::text AS name, data_type::text AS type, \
+            (is_nullable = 'YES')::int8 AS nullable, column_default::text AS dflt \
+            FROM information_schema.columns \
+            WHERE table_schema = current_schema() AND table_name = ? ORDER BY ordinal_position"
+        }
+    }
+}
+
+/**
+Map the native type of a SQLite column onto a value type following SQLite's affinity rules
+ */
+fn sqlite_type(native: &str) -> Option<DatabaseValueType> {
+    Some(match native {
+        "boolean" | "bool" => DatabaseValueType::Bool,
+        "date" => DatabaseValueType::NaiveDate,
+        "time" => DatabaseValueType::NaiveTime,
+        "datetime" | "timestamp" => DatabaseValueType::NaiveDateTime,
+        "json" => DatabaseValueType::Json,
+        "" | "blob" => DatabaseValueType::Binary,
+        v if v.contains("int") => DatabaseValueType::I64,
+        v if v.contains("char") || v.contains("clob") || v.contains("text") => {
+            DatabaseValueType::String
+        }
+        v if v.contains("real") || v.contains("floa") || v.contains("doub") => {
+            DatabaseValueType::F64
+        }
+        v if v.starts_with("numeric") || v.starts_with("decimal") => DatabaseValueType::Decimal,
+        _ => return None,
+    })
+}
+
+/**
+Map the column type of a MySQL column like `int(11) unsigned` onto a value type
+ */
+fn mysql_type(native: &str) -> Option<DatabaseValueType> {
+    if native.starts_with("tinyint(1)") {
+        return Some(DatabaseValueType::Bool);
+    }
+    let unsigned = native.contains("unsigned");
+    let base = native.split(|c| c == '(' || c == ' ').next().unwrap_or("");
+    Some(match base {
+        "bigint" if unsigned => DatabaseValueType::U64,
+        "int" | "integer" | "mediumint" | "smallint" | "tinyint" if unsigned => {
+            DatabaseValueType::U32
+        }
+        "bigint" => DatabaseValueType::I64,
+        "int" | "integer" | "mediumint" => DatabaseValueType::I32,
+        "smallint" | "tinyint" => DatabaseValueType::I16,
+        "bool" | "boolean" => DatabaseValueType::Bool,
+        "float" => DatabaseValueType::F32,
+        "double" | "real" => DatabaseValueType::F64,
+        "decimal" | "numeric" => DatabaseValueType::Decimal,
+        "json" => DatabaseValueType::Json,
+        "char" | "varchar" | "tinytext" | "text" | "mediumtext" | "longtext" | "enum" | "set" => {
+            DatabaseValueType::String
+        }
+        "binary" | "varbinary" | "tinyblob" | "blob" | "mediumblob" | "longblob" => {
+            DatabaseValueType::Binary
+        }
+        "date" => DatabaseValueType::NaiveDate,
+        "time" => DatabaseValueType::NaiveTime,
+        "datetime" => DatabaseValueType::NaiveDateTime,
+        "timestamp" => DatabaseValueType::DateTimeUtc,
+        _ => return None,
+    })
+}
+
+/**
+Map the data type of a Postgres column as reported by `information_schema` onto a value type
+ */
+fn postgres_type(native: &str) -> Option<DatabaseValueType> {
+    Some(match native {
+        "bigint" => DatabaseValueType::I64,
+        "integer" => DatabaseValueType::I32,
+        "smallint" => DatabaseValueType::I16,
+        "boolean" => DatabaseValueType::Bool,
+        "real" => DatabaseValueType::F32,
+        "double precision" => DatabaseValueType::F64,
+        "numeric" => DatabaseValueType::Decimal,
+        "json" | "jsonb" => DatabaseValueType::Json,
+        "uuid" => DatabaseValueType::Uuid,
+        "bytea" => DatabaseValueType::Binary,
+        "text" | "character varying" | "character" | "name" => DatabaseValueType::String,
+        "date" => DatabaseValueType::NaiveDate,
+        "time without time zone" => DatabaseValueType::NaiveTime,
+        "timestamp without time zone" => DatabaseValueType::NaiveDateTime,
+        "timestamp with time zone" => DatabaseValueType::DateTimeUtc,
+        _ => return None,
+    })
+}
+
+/**
+Convert the rows of the `describe_table_query` to `(name, type, nullable, default)` tuples
+
+The native types are mapped onto the matching DatabaseValueType, unknown types fall back
+to String and raise a Warning. A table without columns doesn't exist and raises ProgrammingError.
+ */
+pub(crate) fn table_columns(
+    py: Python,
+    backend: Backend,
+    table: &str,
+    rows: &[Row],
+) -> PyResult<Vec<(String, PyObject, bool, Option<String>)>> {
+    if rows.is_empty() {
+        return Err(errors::ProgrammingError::new_err(format!(
+            "Table {} doesn't exist",
+            table
+        )));
+    }
+    let mut columns = Vec::with_capacity(rows.len());
+    for row in rows {
+        let name: &str = row.get("name").map_err(errors::map_rorm_error)?;
+        let native: &str = row.get("type").map_err(errors::map_rorm_error)?;
+        let nullable: i64 = row.get("nullable").map_err(errors::map_rorm_error)?;
+        let default: Option<&str> = row.get("dflt").map_err(errors::map_rorm_error)?;
+        let lowercase = native.trim().to_lowercase();
+        let value_type = match backend {
+            Backend::SQLite => sqlite_type(&lowercase),
+            Backend::MySQL => mysql_type(&lowercase),
+            Backend::Postgres => postgres_type(&lowercase),
+        };
+        let value_type = match value_type {
+            Some(v) => v,
+            None => {
+                PyErr::warn(
+                    py,
+                    py.get_type::<errors::Warning>(),
+                    &format!(
+                        "Unknown type {} of column {}, falling back to String",
+                        native, name
+                    ),
+                    1,
+                )?;
+                DatabaseValueType::String
+            }
+        };
+        columns.push((
+            name.to_string(),
+            value_type.type_code(py)?,
+            nullable != 0,
+            default.map(|v| v.to_string()),
+        ));
+    }
+    Ok(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_server_version_of_all_backends() {
+        assert_eq!(
+            parse_server_version(
+                Backend::Postgres,
+                "PostgreSQL 14.5 (Debian 14.5-1) on x86_64-pc-linux-gnu, compiled by gcc"
+            )
+            .unwrap(),
+            (14, 5, 0)
+        );
+        assert_eq!(
+            parse_server_version(Backend::Postgres, "PostgreSQL 16beta1").unwrap(),
+            (16, 0, 0)
+        );
+        assert_eq!(
+            parse_server_version(Backend::MySQL, "8.0.30").unwrap(),
+            (8, 0, 30)
+        );
+        assert_eq!(
+            parse_server_version(Backend::MySQL, "10.6.5-MariaDB-1:10.6.5+maria~focal").unwrap(),
+            (10, 6, 5)
+        );
+        assert_eq!(
+            parse_server_version(Backend::SQLite, "3.39.2").unwrap(),
+            (3, 39, 2)
+        );
+    }
+
+    #[test]
+    fn parse_server_version_rejects_unknown_formats() {
+        assert!(parse_server_version(Backend::SQLite, "").is_err());
+        assert!(parse_server_version(Backend::Postgres, "PostgreSQL").is_err());
+        assert!(parse_server_version(Backend::MySQL, "MariaDB 10.6").is_err());
+    }
+
+    #[test]
+    fn sqlite_type_follows_the_affinity_rules() {
+        assert_eq!(sqlite_type("integer"), Some(DatabaseValueType::I64));
+        assert_eq!(sqlite_type("bigint"), Some(DatabaseValueType::I64));
+        assert_eq!(sqlite_type("varchar(255)"), Some(DatabaseValueType::String));
+        assert_eq!(
+            sqlite_type("double precision"),
+            Some(DatabaseValueType::F64)
+        );
+        assert_eq!(
+            sqlite_type("numeric(10, 2)"),
+            Some(DatabaseValueType::Decimal)
+        );
+        assert_eq!(sqlite_type(""), Some(DatabaseValueType::Binary));
+        assert_eq!(sqlite_type("boolean"), Some(DatabaseValueType::Bool));
+        assert_eq!(
+            sqlite_type("datetime"),
+            Some(DatabaseValueType::NaiveDateTime)
+        );
+        assert_eq!(sqlite_type("geometry"), None);
+    }
+
+    #[test]
+    fn mysql_type_respects_unsigned_and_display_width() {
+        assert_eq!(mysql_type("tinyint(1)"), Some(DatabaseValueType::Bool));
+        assert_eq!(mysql_type("tinyint(4)"), Some(DatabaseValueType::I16));
+        assert_eq!(mysql_type("int(11) unsigned"), Some(DatabaseValueType::U32));
+        assert_eq!(
+            mysql_type("bigint(20) unsigned"),
+            Some(DatabaseValueType::U64)
+        );
+        assert_eq!(mysql_type("int(11)"), Some(DatabaseValueType::I32));
+        assert_eq!(
+            mysql_type("decimal(10,2)"),
+            Some(DatabaseValueType::Decimal)
+        );
+        assert_eq!(mysql_type("enum('a','b')"), Some(DatabaseValueType::String));
+        assert_eq!(
+            mysql_type("timestamp"),
+            Some(DatabaseValueType::DateTimeUtc)
+        );
+        assert_eq!(mysql_type("geometry"), None);
+    }
+
+    #[test]
+    fn postgres_type_maps_information_schema_names() {
+        assert_eq!(postgres_type("integer"), Some(DatabaseValueType::I32));
+        assert_eq!(
+            postgres_type("character varying"),
+            Some(DatabaseValueType::String)
+        );
+        assert_eq!(postgres_type("jsonb"), Some(DatabaseValueType::Json));
+        assert_eq!(postgres_type("uuid"), Some(DatabaseValueType::Uuid));
+        assert_eq!(
+            postgres_type("timestamp with time zone"),
+            Some(DatabaseValueType::DateTimeUtc)
+        );
+        assert_eq!(postgres_type("tsvector"), None);
+    }
+}
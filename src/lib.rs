@@ -1,11 +1,22 @@
 use pyo3::prelude::*;
 
+#[macro_use]
+mod macros;
+
 mod common;
+mod cursor;
+mod dsn;
 mod errors;
-mod macros;
+mod introspection;
 mod mysql;
 mod postgres;
+#[cfg(feature = "arrow")]
+mod record_batch;
 mod sqlite;
+#[cfg(feature = "tracing")]
+mod telemetry;
+mod transaction;
+mod types;
 mod utils;
 
 /**
@@ -28,9 +39,12 @@ fn bindings(_py: Python, m: &PyModule) -> PyResult<()> {
     )?;
     mod_err.add("IntegrityError", _py.get_type::<errors::IntegrityError>())?;
     mod_err.add("InternalError", _py.get_type::<errors::InternalError>())?;
-    mod_err.add("InterfaceError", _py.get_type::<errors::ProgrammingError>())?;
     mod_err.add(
-        "InterfaceError",
+        "ProgrammingError",
+        _py.get_type::<errors::ProgrammingError>(),
+    )?;
+    mod_err.add(
+        "NotSupportedError",
         _py.get_type::<errors::NotSupportedError>(),
     )?;
     m.add_submodule(mod_err)?;
@@ -55,8 +69,24 @@ fn bindings(_py: Python, m: &PyModule) -> PyResult<()> {
     utils::utils(_py, mod_utils)?;
     m.add_submodule(mod_utils)?;
 
+    // Module globals required by Database API Spec 2.0,
+    // databases may be shared between threads, but cursors may not
+    m.add("apilevel", "2.0")?;
+    m.add("threadsafety", 2)?;
+    m.add("paramstyle", "qmark")?;
+    types::types(_py, m)?;
+    dsn::dsn(_py, m)?;
+    #[cfg(feature = "tracing")]
+    m.add_function(wrap_pyfunction!(telemetry::init_tracing, m)?)?;
+
     // Generic, non-specific features
     m.add_class::<common::Database>()?;
+    m.add_class::<cursor::Cursor>()?;
+    m.add_class::<cursor::RowStream>()?;
+    m.add_class::<cursor::ChunkStream>()?;
+    m.add_class::<cursor::QueryResult>()?;
+    m.add_class::<transaction::Transaction>()?;
+    m.add_class::<transaction::Savepoint>()?;
     m.add_class::<common::DatabaseValueType>()?;
 
     Ok(())
@@ -1,37 +1,141 @@
 /**
 Macro to expand database queries in a match statement to handle query errors properly
+
+The variant with a conversion function expects the query to return an Option,
+mapping None to Python's None and converting all other values with the function
  */
 macro_rules! handle_db_err {
     ( $py:ident, $query:expr ) => {{
         match $query {
             Ok(value) => value.into_py($py),
-            Err(e) => return Err(BindingError::new_err(e.to_string())),
+            Err(e) => return Err(crate::errors::map_rorm_error(e)),
+        }
+    }};
+    ( $py:ident, $query:expr, $convert:expr ) => {{
+        match $query {
+            Ok(Some(value)) => $convert(value)?.into_py($py),
+            Ok(None) => $py.None(),
+            Err(e) => return Err(crate::errors::map_rorm_error(e)),
+        }
+    }};
+}
+
+/**
+Macro to convert a single column of a row of a database query result to a Python object
+
+NULL values are converted to None for all types.
+Unsigned values are decoded with `types::unsigned_value`, MySQL requires them to be cast to text.
+Times are truncated to microseconds, since Python doesn't support nanosecond precision.
+Truncated times and integers exceeding the declared type emit a Warning.
+JSON values are parsed to Python objects, unless `$parse_json` is false.
+The `$backend` of the connection selects the cast suggested for columns which can't be decoded.
+ */
+macro_rules! convert_value {
+    ( $py:ident, $row:ident, $backend:expr, $col:expr, $col_t:expr ) => {{
+        convert_value!($py, $row, $backend, $col, $col_t, true)
+    }};
+    ( $py:ident, $row:ident, $backend:expr, $col:expr, $col_t:expr, $parse_json:expr ) => {{
+        match $col_t {
+            DatabaseValueType::Null => $py.None(),
+            DatabaseValueType::String => handle_db_err!($py, $row.get::<Option<&str>, &str>($col)),
+            DatabaseValueType::I64 => handle_db_err!($py, $row.get::<Option<i64>, &str>($col)),
+            DatabaseValueType::I32 => match $row.get::<Option<i64>, &str>($col) {
+                Ok(v) => crate::types::check_int_range::<i32, _>($py, $col, *$col_t, v)?,
+                Err(_) => handle_db_err!($py, $row.get::<Option<i32>, &str>($col)),
+            },
+            DatabaseValueType::I16 => match $row.get::<Option<i64>, &str>($col) {
+                Ok(v) => crate::types::check_int_range::<i16, _>($py, $col, *$col_t, v)?,
+                Err(_) => handle_db_err!($py, $row.get::<Option<i16>, &str>($col)),
+            },
+            DatabaseValueType::U64 => {
+                crate::types::unsigned_value($row, $col, $backend)?.into_py($py)
+            }
+            DatabaseValueType::U32 => crate::types::check_int_range::<u32, _>(
+                $py,
+                $col,
+                *$col_t,
+                crate::types::unsigned_value($row, $col, $backend)?,
+            )?,
+            // Backends without a native bool may return integers, e.g. MySQL for comparisons
+            DatabaseValueType::Bool => match $row.get::<Option<bool>, &str>($col) {
+                Ok(v) => v.into_py($py),
+                Err(_) => match $row.get::<Option<i64>, &str>($col) {
+                    Ok(v) => v.map(|v| v != 0).into_py($py),
+                    Err(e) => return Err(crate::errors::map_rorm_error(e)),
+                },
+            },
+            DatabaseValueType::F64 => handle_db_err!($py, $row.get::<Option<f64>, &str>($col)),
+            DatabaseValueType::F32 => handle_db_err!($py, $row.get::<Option<f32>, &str>($col)),
+            DatabaseValueType::Decimal => match crate::types::decimal_text($row, $col, $backend)? {
+                Some(v) => crate::types::decimal_to_py($py, &v)?,
+                None => $py.None(),
+            },
+            DatabaseValueType::Json => handle_db_err!(
+                $py,
+                $row.get::<Option<sqlx::types::Json<serde_json::Value>>, &str>($col),
+                |v: sqlx::types::Json<serde_json::Value>| {
+                    crate::types::json_to_py($py, &v.0.to_string(), $parse_json)
+                }
+            ),
+            DatabaseValueType::Binary => handle_db_err!(
+                $py,
+                $row.get::<Option<Vec<u8>>, &str>($col),
+                |v: Vec<u8>| PyResult::Ok(pyo3::types::PyBytes::new($py, &v))
+            ),
+            DatabaseValueType::Uuid => handle_db_err!(
+                $py,
+                $row.get::<Option<uuid::Uuid>, &str>($col),
+                |v: uuid::Uuid| crate::types::uuid_to_py($py, &v)
+            ),
+            DatabaseValueType::NaiveTime => handle_db_err!(
+                $py,
+                $row.get::<Option<chrono::NaiveTime>, &str>($col),
+                |v: chrono::NaiveTime| {
+                    crate::types::check_precision($py, $col, chrono::Timelike::nanosecond(&v))?;
+                    crate::types::time_to_py($py, v)
+                }
+            ),
+            DatabaseValueType::NaiveDate => handle_db_err!(
+                $py,
+                $row.get::<Option<chrono::NaiveDate>, &str>($col),
+                |v| crate::types::date_to_py($py, v)
+            ),
+            DatabaseValueType::NaiveDateTime => handle_db_err!(
+                $py,
+                $row.get::<Option<chrono::NaiveDateTime>, &str>($col),
+                |v: chrono::NaiveDateTime| {
+                    crate::types::check_precision($py, $col, chrono::Timelike::nanosecond(&v))?;
+                    crate::types::datetime_to_py($py, v)
+                }
+            ),
+            DatabaseValueType::DateTimeUtc => handle_db_err!(
+                $py,
+                $row.get::<Option<chrono::DateTime<chrono::Utc>>, &str>($col),
+                |v: chrono::DateTime<chrono::Utc>| {
+                    crate::types::check_precision($py, $col, chrono::Timelike::nanosecond(&v))?;
+                    crate::types::datetime_utc_to_py($py, v)
+                }
+            ),
         }
     }};
 }
 
 /**
-Macro to convert a row of a database query result to a hashmap of column name -> Python object
+Macro to convert a row of a database query result to a Python dict of column name -> Python object
+
+The keys are inserted in the order of the given columns, so they match the order of the query.
  */
 macro_rules! convert_row {
-    ( $py:ident, $row:ident, $columns:ident ) => {{
-        let mut m = HashMap::new();
+    ( $py:ident, $row:ident, $backend:expr, $columns:ident ) => {{
+        convert_row!($py, $row, $backend, $columns, true)
+    }};
+    ( $py:ident, $row:ident, $backend:expr, $columns:ident, $parse_json:expr ) => {{
+        let m = pyo3::types::PyDict::new($py);
         for (col, col_t) in &$columns {
-            let e = match col_t {
-                DatabaseValueType::Null => $py.None(),
-                DatabaseValueType::String => handle_db_err!($py, $row.get::<&str, &str>(col)),
-                DatabaseValueType::I64 => handle_db_err!($py, $row.get::<i64, &str>(col)),
-                DatabaseValueType::I32 => handle_db_err!($py, $row.get::<i32, &str>(col)),
-                DatabaseValueType::I16 => handle_db_err!($py, $row.get::<i16, &str>(col)),
-                DatabaseValueType::Bool => handle_db_err!($py, $row.get::<bool, &str>(col)),
-                DatabaseValueType::F64 => handle_db_err!($py, $row.get::<f64, &str>(col)),
-                DatabaseValueType::F32 => handle_db_err!($py, $row.get::<f32, &str>(col)),
-                DatabaseValueType::Binary => $py.None(), // TODO
-                DatabaseValueType::NaiveTime => $py.None(), // TODO
-                DatabaseValueType::NaiveDate => $py.None(), // TODO
-                DatabaseValueType::NaiveDateTime => $py.None(), // TODO
-            };
-            m.insert(*col, e);
+            m.set_item(
+                *col,
+                convert_value!($py, $row, $backend, *col, col_t, $parse_json),
+            )?;
         }
         m
     }};
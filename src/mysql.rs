@@ -1,13 +1,48 @@
 use pyo3::prelude::*;
-use rorm::{Database, DatabaseConfiguration, DatabaseDriver};
+use rorm::{DatabaseConfiguration, DatabaseDriver};
 
 use crate::common;
-use crate::errors;
 
-static DEFAULT_HOST: &str = "localhost";
-static DEFAULT_PORT: u16 = 3306;
-static DEFAULT_MAX_CONNECTIONS: u32 = 32;
+pub(crate) static DEFAULT_HOST: &str = "localhost";
+pub(crate) static DEFAULT_PORT: u16 = 3306;
+pub(crate) static DEFAULT_MAX_CONNECTIONS: u32 = 32;
 
+/**
+Build the configuration of the connect functions, filling in the defaults
+ */
+fn configuration(
+    database: String,
+    user: String,
+    password: String,
+    host: Option<String>,
+    port: Option<u16>,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+) -> DatabaseConfiguration {
+    DatabaseConfiguration {
+        driver: DatabaseDriver::MySQL {
+            name: database,
+            host: host.or_else(|| Some(DEFAULT_HOST.to_string())).unwrap(),
+            port: port.or_else(|| Some(DEFAULT_PORT)).unwrap(),
+            user,
+            password,
+        },
+        min_connections: min_connections.or_else(|| Some(1)).unwrap(),
+        max_connections: max_connections
+            .or_else(|| Some(DEFAULT_MAX_CONNECTIONS))
+            .unwrap(),
+    }
+}
+
+/**
+Connect to a MySQL or MariaDB database
+
+Every connection uses the utf8mb4 charset with the utf8mb4_unicode_ci collation, which
+is set by sqlx when the connection is opened, so multibyte strings round-trip unchanged.
+
+TODO: rorm neither passes a charset to sqlx nor offers a hook to configure every
+connection of the pool, so other charsets and collations can't be selected yet.
+ */
 #[pyfunction(module = "rorm_python.bindings.mysql")]
 fn connect(
     py: Python<'_>,
@@ -18,31 +53,70 @@ fn connect(
     port: Option<u16>,
     min_connections: Option<u32>,
     max_connections: Option<u32>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
 ) -> PyResult<&PyAny> {
+    let configuration = configuration(
+        database,
+        user,
+        password,
+        host,
+        port,
+        min_connections,
+        max_connections,
+    );
     pyo3_asyncio::tokio::future_into_py(py, async move {
-        match Database::connect(DatabaseConfiguration {
-            driver: DatabaseDriver::MySQL {
-                name: database,
-                host: host.or_else(|| Some(DEFAULT_HOST.to_string())).unwrap(),
-                port: port.or_else(|| Some(DEFAULT_PORT)).unwrap(),
-                user,
-                password,
-            },
-            min_connections: min_connections.or_else(|| Some(1)).unwrap(),
-            max_connections: max_connections
-                .or_else(|| Some(DEFAULT_MAX_CONNECTIONS))
-                .unwrap(),
-        })
+        common::Database::connect(
+            configuration,
+            connect_timeout,
+            connect_retries,
+            retry_backoff_ms,
+        )
         .await
-        {
-            Ok(v) => Ok(common::Database { db: Box::new(v) }),
-            Err(err) => Err(errors::DatabaseError::new_err(err.to_string())),
-        }
     })
 }
 
+/**
+Connect to a MySQL or MariaDB database like `connect`, but block until the database is connected
+ */
+#[pyfunction(module = "rorm_python.bindings.mysql")]
+fn connect_blocking(
+    py: Python<'_>,
+    database: String,
+    user: String,
+    password: String,
+    host: Option<String>,
+    port: Option<u16>,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+) -> PyResult<common::Database> {
+    let configuration = configuration(
+        database,
+        user,
+        password,
+        host,
+        port,
+        min_connections,
+        max_connections,
+    );
+    common::block_on(
+        py,
+        common::Database::connect(
+            configuration,
+            connect_timeout,
+            connect_retries,
+            retry_backoff_ms,
+        ),
+    )
+}
+
 #[pymodule]
 pub(super) fn mysql(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(connect, m)?)?;
+    m.add_function(wrap_pyfunction!(connect_blocking, m)?)?;
     Ok(())
 }
@@ -1,7 +1,470 @@
+use std::sync::Arc;
+
+use pyo3::exceptions::PyStopAsyncIteration;
 use pyo3::prelude::*;
+use rorm::{DatabaseConfiguration, DatabaseDriver};
+use sqlx::postgres::{PgConnectOptions, PgConnection, PgListener, PgPoolOptions};
+use sqlx::Connection;
+use tokio::sync::{watch, Mutex};
+
+use crate::common;
+use crate::errors;
+
+pub(crate) static DEFAULT_HOST: &str = "localhost";
+pub(crate) static DEFAULT_PORT: u16 = 5432;
+pub(crate) static DEFAULT_MAX_CONNECTIONS: u32 = 32;
+
+/**
+Build the configuration of the connect functions, filling in the defaults
+ */
+fn configuration(
+    database: String,
+    user: String,
+    password: String,
+    host: Option<String>,
+    port: Option<u16>,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+) -> DatabaseConfiguration {
+    DatabaseConfiguration {
+        driver: DatabaseDriver::Postgres {
+            name: database,
+            host: host.or_else(|| Some(DEFAULT_HOST.to_string())).unwrap(),
+            port: port.or_else(|| Some(DEFAULT_PORT)).unwrap(),
+            user,
+            password,
+        },
+        min_connections: min_connections.or_else(|| Some(1)).unwrap(),
+        max_connections: max_connections
+            .or_else(|| Some(DEFAULT_MAX_CONNECTIONS))
+            .unwrap(),
+    }
+}
+
+/**
+Connect to a Postgres database
+
+UUID and timezone-aware datetime parameters are bound as text, so they need a cast to the
+type of their column, e.g. `WHERE id = ?::uuid` or `VALUES (?::timestamptz)`. Without it,
+Postgres raises an error since it doesn't convert text implicitly. The same applies to
+`decimal.Decimal` parameters of numeric columns, e.g. `?::numeric`.
+
+TODO: rorm builds the connect options of the pool itself and offers no hook to configure
+every connection, so startup parameters like `application_name` and `search_path` can't
+be set yet. Use schema-qualified names or `SET search_path` within a transaction instead.
+ */
+#[pyfunction(module = "rorm_python.bindings.postgres")]
+fn connect(
+    py: Python<'_>,
+    database: String,
+    user: String,
+    password: String,
+    host: Option<String>,
+    port: Option<u16>,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+) -> PyResult<&PyAny> {
+    let configuration = configuration(
+        database,
+        user,
+        password,
+        host,
+        port,
+        min_connections,
+        max_connections,
+    );
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        common::Database::connect(
+            configuration,
+            connect_timeout,
+            connect_retries,
+            retry_backoff_ms,
+        )
+        .await
+    })
+}
+
+/**
+Connect to a Postgres database like `connect`, but block until the database is connected
+ */
+#[pyfunction(module = "rorm_python.bindings.postgres")]
+fn connect_blocking(
+    py: Python<'_>,
+    database: String,
+    user: String,
+    password: String,
+    host: Option<String>,
+    port: Option<u16>,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+) -> PyResult<common::Database> {
+    let configuration = configuration(
+        database,
+        user,
+        password,
+        host,
+        port,
+        min_connections,
+        max_connections,
+    );
+    common::block_on(
+        py,
+        common::Database::connect(
+            configuration,
+            connect_timeout,
+            connect_retries,
+            retry_backoff_ms,
+        ),
+    )
+}
+
+static COPY_BATCH_SIZE: usize = 1000;
+
+/**
+Build the options to open a dedicated connection outside of rorm's pool
+
+Other drivers raise NotSupportedError for the given feature.
+ */
+fn connect_options(driver: DatabaseDriver, feature: &str) -> PyResult<PgConnectOptions> {
+    match driver {
+        DatabaseDriver::Postgres {
+            name,
+            host,
+            port,
+            user,
+            password,
+        } => Ok(PgConnectOptions::new()
+            .host(&host)
+            .port(port)
+            .username(&user)
+            .password(&password)
+            .database(&name)),
+        _ => Err(errors::NotSupportedError::new_err(format!(
+            "{} is only supported by Postgres",
+            feature
+        ))),
+    }
+}
+
+/**
+Quote a possibly schema-qualified identifier for the use in a COPY statement
+ */
+fn quote_identifier(identifier: &str) -> String {
+    identifier
+        .split('.')
+        .map(|part| format!("\"{}\"", part.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/**
+Format binary data in the hex format of bytea
+ */
+fn encode_copy_bytea(v: &[u8]) -> String {
+    let hex: String = v.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("\\x{}", hex)
+}
+
+/**
+Append a value in the text format of COPY, escaping the delimiters and backslashes
+ */
+fn encode_copy_value(value: &common::DatabaseValue, out: &mut String) -> PyResult<()> {
+    let text = match value {
+        common::DatabaseValue::Null => {
+            out.push_str("\\N");
+            return Ok(());
+        }
+        common::DatabaseValue::String(v)
+        | common::DatabaseValue::Decimal(v)
+        | common::DatabaseValue::Json(v) => v.clone(),
+        common::DatabaseValue::I64(v) => v.to_string(),
+        common::DatabaseValue::Bool(v) => (if *v { "t" } else { "f" }).to_string(),
+        common::DatabaseValue::F64(v) => v.to_string(),
+        common::DatabaseValue::Binary(v) => encode_copy_bytea(v),
+        common::DatabaseValue::Buffer(v) => encode_copy_bytea(v.as_bytes()),
+        common::DatabaseValue::Uuid(v) => v.to_string(),
+        common::DatabaseValue::NaiveTime(v) => v.format("%H:%M:%S%.f").to_string(),
+        common::DatabaseValue::NaiveDate(v) => v.format("%Y-%m-%d").to_string(),
+        common::DatabaseValue::NaiveDateTime(v) => v.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+        common::DatabaseValue::DateTimeUtc(v) => v.format("%Y-%m-%d %H:%M:%S%.f+00").to_string(),
+        common::DatabaseValue::List(_) => {
+            return Err(errors::NotSupportedError::new_err(
+                "Lists can't be copied as a single value",
+            ))
+        }
+    };
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    Ok(())
+}
+
+/**
+Convert the next batch of rows of the Python iterator to the text format of COPY
+
+Returns None when the iterator is exhausted.
+ */
+fn encode_copy_batch(py: Python, rows: &PyObject, columns: usize) -> PyResult<Option<String>> {
+    let mut rows = rows
+        .as_ref(py)
+        .iter()?
+        .map(|row| row.and_then(|row| row.extract::<Vec<common::DatabaseValue>>()));
+    encode_copy_rows(&mut rows, columns)
+}
+
+/**
+Convert up to `COPY_BATCH_SIZE` rows to the text format of COPY, consuming only those rows
+
+Returns None if there are no rows left.
+ */
+fn encode_copy_rows(
+    rows: &mut impl Iterator<Item = PyResult<Vec<common::DatabaseValue>>>,
+    columns: usize,
+) -> PyResult<Option<String>> {
+    let mut buffer = String::new();
+    let mut count = 0;
+    for values in rows.by_ref().take(COPY_BATCH_SIZE) {
+        let values = values?;
+        if values.len() != columns {
+            return Err(errors::ProgrammingError::new_err(format!(
+                "Row has {} values, but {} columns were given",
+                values.len(),
+                columns
+            )));
+        }
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                buffer.push('\t');
+            }
+            encode_copy_value(value, &mut buffer)?;
+        }
+        buffer.push('\n');
+        count += 1;
+    }
+    Ok(if count == 0 { None } else { Some(buffer) })
+}
+
+/**
+Bulk load the rows of a Python iterable into a table using `COPY ... FROM STDIN`
+
+The rows are converted in batches while they are sent, so the iterable isn't materialized.
+Returns the number of copied rows.
+ */
+pub(crate) async fn copy_records(
+    driver: DatabaseDriver,
+    table: String,
+    columns: Vec<String>,
+    rows: PyObject,
+) -> PyResult<u64> {
+    let options = connect_options(driver, "COPY")?;
+    let statement = format!(
+        "COPY {} ({}) FROM STDIN WITH (FORMAT text)",
+        quote_identifier(&table),
+        columns
+            .iter()
+            .map(|v| quote_identifier(v))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let mut connection = PgConnection::connect_with(&options)
+        .await
+        .map_err(errors::map_sqlx_error)?;
+    let mut copy = connection
+        .copy_in_raw(&statement)
+        .await
+        .map_err(errors::map_sqlx_error)?;
+    loop {
+        let batch = match Python::with_gil(|py| encode_copy_batch(py, &rows, columns.len())) {
+            Ok(Some(batch)) => batch,
+            Ok(None) => break,
+            Err(err) => {
+                let _ = copy.abort(err.to_string()).await;
+                return Err(err);
+            }
+        };
+        copy.send(batch.into_bytes())
+            .await
+            .map_err(errors::map_sqlx_error)?;
+    }
+    copy.finish().await.map_err(errors::map_sqlx_error)
+}
+
+/**
+Connect a listener for the notifications of a channel on its own connection
+
+The connection is opened outside of rorm's pool, since notifications are only
+delivered to the connection which executed LISTEN.
+ */
+pub(crate) async fn listen(driver: DatabaseDriver, channel: String) -> PyResult<Listener> {
+    let options = connect_options(driver, "LISTEN")?;
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+        .map_err(errors::map_sqlx_error)?;
+    let mut listener = PgListener::connect_with(&pool)
+        .await
+        .map_err(errors::map_sqlx_error)?;
+    listener
+        .listen(&channel)
+        .await
+        .map_err(errors::map_sqlx_error)?;
+    let (close_sender, closed) = watch::channel(false);
+    Ok(Listener {
+        channel,
+        listener: Arc::new(Mutex::new(Some(listener))),
+        close_sender,
+        closed,
+    })
+}
+
+/**
+Asynchronous iterator over the payloads of the notifications of a Postgres channel
+ */
+#[pyclass(module = "rorm_python.bindings.postgres")]
+pub(crate) struct Listener {
+    #[pyo3(get)]
+    channel: String,
+    listener: Arc<Mutex<Option<PgListener>>>,
+    close_sender: watch::Sender<bool>,
+    closed: watch::Receiver<bool>,
+}
+
+#[pymethods]
+impl Listener {
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__<'p>(&self, py: Python<'p>) -> PyResult<Option<&'p PyAny>> {
+        let listener = self.listener.clone();
+        // The cloned receiver sees the close even if it happened before waiting for it
+        let mut closed = self.closed.clone();
+        let awaitable = pyo3_asyncio::tokio::future_into_py(py, async move {
+            let next = async {
+                match listener.lock().await.as_mut() {
+                    Some(listener) => Some(listener.recv().await),
+                    None => None,
+                }
+            };
+            tokio::select! {
+                next = next => match next {
+                    Some(Ok(notification)) => Ok(notification.payload().to_string()),
+                    Some(Err(err)) => Err(errors::map_sqlx_error(err)),
+                    None => Err(PyStopAsyncIteration::new_err(())),
+                },
+                _ = closed.changed() => Err(PyStopAsyncIteration::new_err(())),
+            }
+        })?;
+        Ok(Some(awaitable))
+    }
+
+    /**
+    Stop listening and close the listener's connection, which ends the iteration
+
+    A pending `__anext__` is woken up and raises StopAsyncIteration.
+     */
+    fn close<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        // The listener keeps a receiver itself, so sending can't fail
+        let _ = self.close_sender.send(true);
+        let listener = self.listener.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            listener.lock().await.take();
+            Ok(())
+        })
+    }
+}
 
 #[pymodule]
 pub(super) fn postgres(_py: Python, m: &PyModule) -> PyResult<()> {
-    let _m = m;
+    m.add_function(wrap_pyfunction!(connect, m)?)?;
+    m.add_function(wrap_pyfunction!(connect_blocking, m)?)?;
+    m.add_class::<Listener>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(value: common::DatabaseValue) -> String {
+        let mut out = String::new();
+        encode_copy_value(&value, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn encode_copy_value_escapes_text() {
+        assert_eq!(encode(common::DatabaseValue::Null), "\\N");
+        assert_eq!(
+            encode(common::DatabaseValue::String("a\tb\nc\\d".to_string())),
+            "a\\tb\\nc\\\\d"
+        );
+        assert_eq!(
+            encode(common::DatabaseValue::String("\\N".to_string())),
+            "\\\\N"
+        );
+    }
+
+    #[test]
+    fn encode_copy_value_formats_values() {
+        assert_eq!(encode(common::DatabaseValue::I64(-3)), "-3");
+        assert_eq!(encode(common::DatabaseValue::Bool(true)), "t");
+        assert_eq!(
+            encode(common::DatabaseValue::Binary(vec![0, 255])),
+            "\\\\x00ff"
+        );
+        assert!(
+            encode_copy_value(&common::DatabaseValue::List(vec![]), &mut String::new()).is_err()
+        );
+    }
+
+    fn copy_rows(count: usize) -> impl Iterator<Item = PyResult<Vec<common::DatabaseValue>>> {
+        (0..count as i64).map(|v| {
+            Ok(vec![
+                common::DatabaseValue::I64(v),
+                common::DatabaseValue::String("a".to_string()),
+            ])
+        })
+    }
+
+    #[test]
+    fn encode_copy_rows_returns_none_without_rows() {
+        assert_eq!(encode_copy_rows(&mut copy_rows(0), 2).unwrap(), None);
+    }
+
+    #[test]
+    fn encode_copy_rows_splits_exact_multiples_of_the_batch_size() {
+        let mut rows = copy_rows(2 * COPY_BATCH_SIZE);
+        let first = encode_copy_rows(&mut rows, 2).unwrap().unwrap();
+        assert_eq!(first.lines().count(), COPY_BATCH_SIZE);
+        assert!(first.starts_with("0\ta\n"));
+        let second = encode_copy_rows(&mut rows, 2).unwrap().unwrap();
+        assert_eq!(second.lines().count(), COPY_BATCH_SIZE);
+        assert!(second.starts_with(&format!("{}\ta\n", COPY_BATCH_SIZE)));
+        assert_eq!(encode_copy_rows(&mut rows, 2).unwrap(), None);
+    }
+
+    #[test]
+    fn encode_copy_rows_rejects_rows_of_the_wrong_length() {
+        assert!(encode_copy_rows(&mut copy_rows(1), 3).is_err());
+    }
+
+    #[test]
+    fn quote_identifier_quotes_every_part() {
+        assert_eq!(quote_identifier("public.users"), "\"public\".\"users\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+    }
+}
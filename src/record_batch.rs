@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BinaryArray, BooleanArray, Date32Array, Float32Array, Float64Array, Int16Array,
+    Int32Array, Int64Array, NullArray, StringArray, Time64MicrosecondArray,
+    TimestampMicrosecondArray, UInt32Array, UInt64Array,
+};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::pyarrow::PyArrowConvert;
+use arrow::record_batch::RecordBatch;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use pyo3::prelude::*;
+use rorm::row::Row;
+
+use crate::common::{Backend, DatabaseValueType};
+use crate::errors;
+
+/**
+Macro to decode all values of a column of the given rows, NULL values are decoded as None
+ */
+macro_rules! decode_column {
+    ( $rows:ident, $col:ident, $t:ty ) => {{
+        let mut values: Vec<Option<$t>> = Vec::with_capacity($rows.len());
+        for row in $rows {
+            values.push(
+                row.get::<Option<$t>, &str>($col)
+                    .map_err(errors::map_rorm_error)?,
+            );
+        }
+        values
+    }};
+}
+
+/**
+Decode the values of an unsigned column with `types::unsigned_value`,
+raising DataError for values which don't fit into the unsigned type
+ */
+fn unsigned_column<T: TryFrom<u64>>(
+    rows: &[Row],
+    col: &str,
+    backend: Backend,
+) -> PyResult<Vec<Option<T>>> {
+    rows.iter()
+        .map(
+            |row| match crate::types::unsigned_value(row, col, backend)? {
+                Some(v) => T::try_from(v).map(Some).map_err(|_| {
+                    errors::DataError::new_err(format!(
+                        "Value {} of column {} doesn't fit into its unsigned type",
+                        v, col
+                    ))
+                }),
+                None => Ok(None),
+            },
+        )
+        .collect()
+}
+
+/**
+Build the Arrow array and its data type for a single column of the given rows
+ */
+fn build_array(
+    rows: &[Row],
+    backend: Backend,
+    col: &str,
+    col_t: DatabaseValueType,
+) -> PyResult<(ArrayRef, DataType)> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("the epoch is a valid date");
+    Ok(match col_t {
+        DatabaseValueType::Null => (Arc::new(NullArray::new(rows.len())), DataType::Null),
+        DatabaseValueType::String => (
+            Arc::new(StringArray::from(decode_column!(rows, col, &str))),
+            DataType::Utf8,
+        ),
+        DatabaseValueType::I64 => (
+            Arc::new(Int64Array::from(decode_column!(rows, col, i64))),
+            DataType::Int64,
+        ),
+        DatabaseValueType::I32 => (
+            Arc::new(Int32Array::from(decode_column!(rows, col, i32))),
+            DataType::Int32,
+        ),
+        DatabaseValueType::I16 => (
+            Arc::new(Int16Array::from(decode_column!(rows, col, i16))),
+            DataType::Int16,
+        ),
+        DatabaseValueType::U64 => (
+            Arc::new(UInt64Array::from(unsigned_column::<u64>(
+                rows, col, backend,
+            )?)),
+            DataType::UInt64,
+        ),
+        DatabaseValueType::U32 => (
+            Arc::new(UInt32Array::from(unsigned_column::<u32>(
+                rows, col, backend,
+            )?)),
+            DataType::UInt32,
+        ),
+        DatabaseValueType::Bool => (
+            Arc::new(BooleanArray::from(decode_column!(rows, col, bool))),
+            DataType::Boolean,
+        ),
+        DatabaseValueType::F64 => (
+            Arc::new(Float64Array::from(decode_column!(rows, col, f64))),
+            DataType::Float64,
+        ),
+        DatabaseValueType::F32 => (
+            Arc::new(Float32Array::from(decode_column!(rows, col, f32))),
+            DataType::Float32,
+        ),
+        DatabaseValueType::Decimal => {
+            let values = rows
+                .iter()
+                .map(|row| crate::types::decimal_text(row, col, backend))
+                .collect::<PyResult<Vec<Option<String>>>>()?;
+            (Arc::new(StringArray::from(values)), DataType::Utf8)
+        }
+        DatabaseValueType::Json => {
+            let values: Vec<Option<String>> =
+                decode_column!(rows, col, sqlx::types::Json<serde_json::Value>)
+                    .into_iter()
+                    .map(|v| v.map(|v| v.0.to_string()))
+                    .collect();
+            (Arc::new(StringArray::from(values)), DataType::Utf8)
+        }
+        DatabaseValueType::Binary => {
+            let values = decode_column!(rows, col, Vec<u8>);
+            let values: Vec<Option<&[u8]>> = values.iter().map(|v| v.as_deref()).collect();
+            (
+                Arc::new(BinaryArray::from_opt_vec(values)),
+                DataType::Binary,
+            )
+        }
+        DatabaseValueType::Uuid => {
+            let values: Vec<Option<String>> = decode_column!(rows, col, uuid::Uuid)
+                .into_iter()
+                .map(|v| v.map(|v| v.to_string()))
+                .collect();
+            (Arc::new(StringArray::from(values)), DataType::Utf8)
+        }
+        DatabaseValueType::NaiveTime => {
+            let values: Vec<Option<i64>> = decode_column!(rows, col, NaiveTime)
+                .into_iter()
+                .map(|v| {
+                    v.map(|v| {
+                        v.num_seconds_from_midnight() as i64 * 1_000_000
+                            + (v.nanosecond() / 1000).min(999_999) as i64
+                    })
+                })
+                .collect();
+            (
+                Arc::new(Time64MicrosecondArray::from(values)),
+                DataType::Time64(TimeUnit::Microsecond),
+            )
+        }
+        DatabaseValueType::NaiveDate => {
+            let values: Vec<Option<i32>> = decode_column!(rows, col, NaiveDate)
+                .into_iter()
+                .map(|v| v.map(|v| (v - epoch).num_days() as i32))
+                .collect();
+            (Arc::new(Date32Array::from(values)), DataType::Date32)
+        }
+        DatabaseValueType::NaiveDateTime => {
+            let values: Vec<Option<i64>> = decode_column!(rows, col, NaiveDateTime)
+                .into_iter()
+                .map(|v| v.map(|v| v.and_utc().timestamp_micros()))
+                .collect();
+            (
+                Arc::new(TimestampMicrosecondArray::from(values)),
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+            )
+        }
+        DatabaseValueType::DateTimeUtc => {
+            let values: Vec<Option<i64>> = decode_column!(rows, col, DateTime<Utc>)
+                .into_iter()
+                .map(|v| v.map(|v| v.timestamp_micros()))
+                .collect();
+            (
+                Arc::new(TimestampMicrosecondArray::from(values).with_timezone("UTC".to_string())),
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".to_string())),
+            )
+        }
+    })
+}
+
+/**
+Convert the given rows to a PyArrow RecordBatch, building the columnar arrays directly in Rust
+
+Times and timestamps are truncated to microseconds, like their conversion to Python objects.
+Decimals are exported as strings, since their precision and scale aren't known per column,
+and JSON values and UUIDs are exported as their text representation.
+ */
+pub(crate) fn to_pyarrow(
+    py: Python,
+    backend: Backend,
+    columns: &[(String, DatabaseValueType)],
+    rows: &[Row],
+) -> PyResult<PyObject> {
+    let mut fields = Vec::with_capacity(columns.len());
+    let mut arrays = Vec::with_capacity(columns.len());
+    for (col, col_t) in columns {
+        let (array, data_type) = build_array(rows, backend, col, *col_t)?;
+        fields.push(Field::new(col, data_type, true));
+        arrays.push(array);
+    }
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+        .map_err(|err| errors::InternalError::new_err(err.to_string()))?;
+    batch.to_pyarrow(py)
+}
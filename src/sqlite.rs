@@ -1,36 +1,289 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use pyo3::prelude::*;
-use rorm::{Database, DatabaseConfiguration, DatabaseDriver};
+use rorm::{DatabaseConfiguration, DatabaseDriver};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use sqlx::Connection;
 
 use crate::common;
 use crate::errors;
 
-static DEFAULT_MAX_CONNECTIONS: u32 = 16;
+pub(crate) static DEFAULT_MAX_CONNECTIONS: u32 = 16;
 
-#[pyfunction(module = "rorm_python.bindings.sqlite")]
-fn connect(
-    py: Python<'_>,
+static JOURNAL_MODES: [&str; 6] = ["DELETE", "TRUNCATE", "PERSIST", "MEMORY", "WAL", "OFF"];
+
+/**
+Build the PRAGMA statements for the given options, raising ProgrammingError for unknown values
+
+Except for `journal_mode`, which is stored in the database file, the PRAGMAs only apply to the
+connection executing them. rorm offers no hook to configure every connection of the pool, and
+the pool may replace a connection at any time, so they raise NotSupportedError instead of
+turning off silently. sqlx already enables foreign keys and a busy timeout on every connection.
+ */
+fn build_pragmas(
+    journal_mode: Option<String>,
+    synchronous: Option<String>,
+    foreign_keys: Option<bool>,
+    busy_timeout: Option<u32>,
+) -> PyResult<Vec<String>> {
+    let per_connection = [
+        ("synchronous", synchronous.is_some()),
+        ("foreign_keys", foreign_keys.is_some()),
+        ("busy_timeout", busy_timeout.is_some()),
+    ];
+    let used: Vec<&str> = per_connection
+        .iter()
+        .filter(|(_, used)| *used)
+        .map(|(name, _)| *name)
+        .collect();
+    if !used.is_empty() {
+        return Err(errors::NotSupportedError::new_err(format!(
+            "{} can't be applied to every connection of the pool",
+            used.join(", ")
+        )));
+    }
+    let mut pragmas = vec![];
+    if let Some(mode) = journal_mode {
+        let mode = mode.to_uppercase();
+        if !JOURNAL_MODES.contains(&mode.as_str()) {
+            return Err(errors::ProgrammingError::new_err(format!(
+                "Unknown journal mode {}, expected one of {}",
+                mode,
+                JOURNAL_MODES.join(", ")
+            )));
+        }
+        pragmas.push(format!("PRAGMA journal_mode = {}", mode));
+    }
+    Ok(pragmas)
+}
+
+/**
+Connect to a SQLite database file and apply the PRAGMAs afterwards
+ */
+async fn open(
     filename: String,
     min_connections: Option<u32>,
     max_connections: Option<u32>,
-) -> PyResult<&PyAny> {
-    pyo3_asyncio::tokio::future_into_py(py, async move {
-        match Database::connect(DatabaseConfiguration {
+    pragmas: Vec<String>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+) -> PyResult<common::Database> {
+    let database = common::Database::connect(
+        DatabaseConfiguration {
             driver: DatabaseDriver::SQLite { filename },
             min_connections: min_connections.or_else(|| Some(1)).unwrap(),
             max_connections: max_connections
                 .or_else(|| Some(DEFAULT_MAX_CONNECTIONS))
                 .unwrap(),
-        })
-        .await
-        {
-            Ok(v) => Ok(common::Database { db: Box::new(v) }),
-            Err(err) => Err(errors::DatabaseError::new_err(err.to_string())),
+        },
+        connect_timeout,
+        connect_retries,
+        retry_backoff_ms,
+    )
+    .await?;
+    let db = database.db()?;
+    for pragma in &pragmas {
+        db.raw_sql(pragma, None, None)
+            .await
+            .map_err(errors::map_rorm_error)?;
+    }
+    Ok(database)
+}
+
+/**
+Connect to a SQLite database file, optionally applying the common PRAGMAs afterwards
+
+The `journal_mode` is applied once after connecting, since it's stored in the database file.
+The others only apply to the connection which executed them and raise NotSupportedError,
+see `build_pragmas`.
+ */
+#[pyfunction(module = "rorm_python.bindings.sqlite")]
+fn connect(
+    py: Python<'_>,
+    filename: String,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+    journal_mode: Option<String>,
+    synchronous: Option<String>,
+    foreign_keys: Option<bool>,
+    busy_timeout: Option<u32>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+) -> PyResult<&PyAny> {
+    let pragmas = build_pragmas(journal_mode, synchronous, foreign_keys, busy_timeout)?;
+    pyo3_asyncio::tokio::future_into_py(
+        py,
+        open(
+            filename,
+            min_connections,
+            max_connections,
+            pragmas,
+            connect_timeout,
+            connect_retries,
+            retry_backoff_ms,
+        ),
+    )
+}
+
+/**
+Connect to a SQLite database file like `connect`, but block until the database is connected
+ */
+#[pyfunction(module = "rorm_python.bindings.sqlite")]
+fn connect_blocking(
+    py: Python<'_>,
+    filename: String,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+    journal_mode: Option<String>,
+    synchronous: Option<String>,
+    foreign_keys: Option<bool>,
+    busy_timeout: Option<u32>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+) -> PyResult<common::Database> {
+    let pragmas = build_pragmas(journal_mode, synchronous, foreign_keys, busy_timeout)?;
+    common::block_on(
+        py,
+        open(
+            filename,
+            min_connections,
+            max_connections,
+            pragmas,
+            connect_timeout,
+            connect_retries,
+            retry_backoff_ms,
+        ),
+    )
+}
+
+/**
+Connect to a SQLite database file like `connect` and execute the schema script afterwards
+
+The script is executed like `Database.executescript`, i.e. within a transaction which is
+rolled back when a statement fails, in which case the database is closed again. This is
+mostly useful to set up databases for tests and examples in a single call.
+ */
+#[pyfunction(module = "rorm_python.bindings.sqlite")]
+fn connect_with_schema(
+    py: Python<'_>,
+    filename: String,
+    schema_sql: String,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+    journal_mode: Option<String>,
+    synchronous: Option<String>,
+    foreign_keys: Option<bool>,
+    busy_timeout: Option<u32>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+) -> PyResult<&PyAny> {
+    let pragmas = build_pragmas(journal_mode, synchronous, foreign_keys, busy_timeout)?;
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        let database = open(
+            filename,
+            min_connections,
+            max_connections,
+            pragmas,
+            connect_timeout,
+            connect_retries,
+            retry_backoff_ms,
+        )
+        .await?;
+        let script = Python::with_gil(|py| database.script(py, schema_sql))?;
+        script.await?;
+        Ok(database)
+    })
+}
+
+static IN_MEMORY_DATABASES: AtomicUsize = AtomicUsize::new(0);
+
+/**
+Connect to a new in-memory SQLite database, which is mostly useful for tests
+
+All connections of the pool share the same database, which is named by a URI filename with
+a shared cache. An additional connection outside of the pool keeps it alive while the pool
+replaces its connections, until the database is closed. Since the cache is shared, concurrent
+writes may raise OperationalError for locked tables instead of waiting for each other.
+ */
+#[pyfunction(module = "rorm_python.bindings.sqlite")]
+fn connect_in_memory(
+    py: Python<'_>,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+) -> PyResult<&PyAny> {
+    let filename = format!(
+        "file:rorm-memory-{}?mode=memory&cache=shared",
+        IN_MEMORY_DATABASES.fetch_add(1, Ordering::Relaxed)
+    );
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        let options = SqliteConnectOptions::new()
+            .filename(&filename)
+            .create_if_missing(true);
+        let mut keep_alive = SqliteConnection::connect_with(&options)
+            .await
+            .map_err(errors::map_sqlx_error)?;
+        // Without URI filenames, SQLite would have created a file of that name instead
+        let file: String =
+            sqlx::query_scalar("SELECT file FROM pragma_database_list WHERE name = 'main'")
+                .fetch_one(&mut keep_alive)
+                .await
+                .map_err(errors::map_sqlx_error)?;
+        if !file.is_empty() {
+            return Err(errors::InterfaceError::new_err(format!(
+                "SQLite doesn't support URI filenames, created the file {} instead",
+                file
+            )));
         }
+        let mut database = common::Database::connect(
+            DatabaseConfiguration {
+                driver: DatabaseDriver::SQLite { filename },
+                min_connections: min_connections.unwrap_or(1),
+                max_connections: max_connections.unwrap_or(1),
+            },
+            connect_timeout,
+            connect_retries,
+            retry_backoff_ms,
+        )
+        .await?;
+        database.keep_alive = Some(keep_alive);
+        Ok(database)
     })
 }
 
 #[pymodule]
 pub(super) fn sqlite(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(connect, m)?)?;
+    m.add_function(wrap_pyfunction!(connect_blocking, m)?)?;
+    m.add_function(wrap_pyfunction!(connect_with_schema, m)?)?;
+    m.add_function(wrap_pyfunction!(connect_in_memory, m)?)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_pragmas_normalizes_the_journal_mode() {
+        assert_eq!(
+            build_pragmas(Some("wal".to_string()), None, None, None).unwrap(),
+            vec!["PRAGMA journal_mode = WAL"]
+        );
+        assert!(build_pragmas(None, None, None, None).unwrap().is_empty());
+        assert!(build_pragmas(Some("fast".to_string()), None, None, None).is_err());
+    }
+
+    #[test]
+    fn build_pragmas_rejects_per_connection_pragmas() {
+        assert!(build_pragmas(None, Some("OFF".to_string()), None, None).is_err());
+        assert!(build_pragmas(None, None, Some(true), None).is_err());
+        assert!(build_pragmas(None, None, None, Some(500)).is_err());
+    }
+}
@@ -0,0 +1,26 @@
+use pyo3::prelude::*;
+use tracing_subscriber::EnvFilter;
+
+use crate::errors;
+
+/**
+Install a default tracing subscriber which prints the spans of the bindings to stderr
+
+The filter uses the syntax of `RUST_LOG` and defaults to the `RUST_LOG` environment
+variable or `info`. Applications which install their own subscriber don't need this.
+Raises InterfaceError if a global subscriber has already been installed.
+ */
+#[pyfunction(module = "rorm_python.bindings")]
+pub(crate) fn init_tracing(filter: Option<String>) -> PyResult<()> {
+    let filter = match filter {
+        Some(filter) => EnvFilter::try_new(filter)
+            .map_err(|err| errors::ProgrammingError::new_err(err.to_string()))?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .try_init()
+        .map_err(|err| errors::InterfaceError::new_err(err.to_string()))
+}
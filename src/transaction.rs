@@ -0,0 +1,376 @@
+use std::sync::Arc;
+
+use pyo3::prelude::*;
+use rorm::row::Row;
+use rorm::value::Value;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::common;
+use crate::cursor;
+use crate::errors;
+
+/**
+Commands sent to the task which owns the rorm transaction
+ */
+pub(crate) enum Command {
+    Execute {
+        query: String,
+        params: Vec<common::DatabaseValue>,
+        reply: oneshot::Sender<Result<Vec<Row>, rorm::error::Error>>,
+    },
+    Commit(oneshot::Sender<Result<(), rorm::error::Error>>),
+    Rollback(oneshot::Sender<Result<(), rorm::error::Error>>),
+}
+
+/**
+Start a transaction in its own task, since the rorm transaction borrows the database
+
+The transaction is rolled back when all senders are dropped without finishing it.
+ */
+pub(crate) async fn start(db: Arc<rorm::Database>) -> PyResult<mpsc::UnboundedSender<Command>> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let (started_sender, started) = oneshot::channel();
+    let task = async move {
+        let mut tx = match db.start_transaction().await {
+            Ok(tx) => {
+                let _ = started_sender.send(Ok(()));
+                tx
+            }
+            Err(err) => {
+                let _ = started_sender.send(Err(err));
+                return;
+            }
+        };
+        while let Some(command) = receiver.recv().await {
+            match command {
+                Command::Execute {
+                    query,
+                    params,
+                    reply,
+                } => {
+                    let values: Vec<Value> = params.iter().map(|v| v.as_value()).collect();
+                    let _ = reply.send(db.raw_sql(&query, Some(&values), Some(&mut tx)).await);
+                }
+                Command::Commit(reply) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::Span::current().record("outcome", "commit");
+                    let _ = reply.send(tx.commit().await);
+                    return;
+                }
+                Command::Rollback(reply) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::Span::current().record("outcome", "rollback");
+                    let _ = reply.send(tx.rollback().await);
+                    return;
+                }
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("outcome", "dropped");
+    };
+    #[cfg(feature = "tracing")]
+    let task = tracing::Instrument::instrument(
+        task,
+        tracing::info_span!("transaction", outcome = tracing::field::Empty),
+    );
+    tokio::spawn(task);
+    match started.await {
+        Ok(Ok(())) => Ok(sender),
+        Ok(Err(err)) => Err(errors::map_rorm_error(err)),
+        Err(_) => Err(errors::InternalError::new_err(
+            "Transaction task exited unexpectedly",
+        )),
+    }
+}
+
+/**
+Execute a raw SQL query within the transaction of the task behind the channel
+ */
+pub(crate) async fn execute(
+    sender: &mpsc::UnboundedSender<Command>,
+    query: String,
+    params: Vec<common::DatabaseValue>,
+) -> PyResult<Vec<Row>> {
+    let (reply, result) = oneshot::channel();
+    sender
+        .send(Command::Execute {
+            query,
+            params,
+            reply,
+        })
+        .map_err(|_| out_of_sync())?;
+    result
+        .await
+        .map_err(|_| out_of_sync())?
+        .map_err(errors::map_rorm_error)
+}
+
+/**
+Finish the transaction of the task behind the channel by either committing or rolling it back
+ */
+pub(crate) async fn finish(sender: mpsc::UnboundedSender<Command>, commit: bool) -> PyResult<()> {
+    let (reply, result) = oneshot::channel();
+    let command = if commit {
+        Command::Commit(reply)
+    } else {
+        Command::Rollback(reply)
+    };
+    sender.send(command).map_err(|_| out_of_sync())?;
+    match result.await {
+        Ok(result) => result.map_err(errors::map_rorm_error),
+        Err(_) => Err(out_of_sync()),
+    }
+}
+
+static ISOLATION_LEVELS: [&str; 4] = [
+    "READ UNCOMMITTED",
+    "READ COMMITTED",
+    "REPEATABLE READ",
+    "SERIALIZABLE",
+];
+
+/**
+Build the statement which sets the isolation level as first statement of a transaction
+
+Returns None if the backend doesn't need a statement for the level.
+ */
+pub(crate) fn isolation_statement(
+    backend: common::Backend,
+    level: &str,
+) -> PyResult<Option<String>> {
+    let level = level.trim().to_uppercase();
+    if !ISOLATION_LEVELS.contains(&level.as_str()) {
+        return Err(errors::ProgrammingError::new_err(format!(
+            "Unknown isolation level {}, expected one of {}",
+            level,
+            ISOLATION_LEVELS.join(", ")
+        )));
+    }
+    match backend {
+        common::Backend::Postgres => Ok(Some(format!("SET TRANSACTION ISOLATION LEVEL {}", level))),
+        common::Backend::SQLite if level == "SERIALIZABLE" => Ok(None),
+        common::Backend::SQLite => Err(errors::NotSupportedError::new_err(format!(
+            "SQLite doesn't support the isolation level {}",
+            level
+        ))),
+        common::Backend::MySQL => Err(errors::NotSupportedError::new_err(
+            "MySQL requires the isolation level before starting the transaction, \
+             which rorm doesn't support",
+        )),
+    }
+}
+
+/**
+Error raised when a transaction is used after it has been finished
+ */
+pub(crate) fn out_of_sync() -> PyErr {
+    errors::InternalError::new_err("Transaction out of sync, it has already been finished")
+}
+
+/**
+Wrapper class around a running transaction of a database
+ */
+#[pyclass(module = "rorm_python")]
+pub(crate) struct Transaction {
+    pub(crate) database: Py<common::Database>,
+    pub(crate) sender: Option<mpsc::UnboundedSender<Command>>,
+    pub(crate) savepoints: u32,
+}
+
+impl Transaction {
+    /**
+    Retrieve the channel to the transaction task, unless it has already been finished
+     */
+    pub(crate) fn sender(&self) -> PyResult<mpsc::UnboundedSender<Command>> {
+        match &self.sender {
+            Some(sender) => Ok(sender.clone()),
+            None => Err(out_of_sync()),
+        }
+    }
+
+    /**
+    Retrieve an executor for the queries of this transaction
+     */
+    pub(crate) fn executor(&self, py: Python) -> PyResult<common::Executor> {
+        let database = self.database.borrow(py);
+        Ok(common::Executor {
+            target: common::Target::Transaction(self.sender()?),
+            backend: database.backend,
+            logger: database.logger(py),
+            timeout: None,
+            slow_query_threshold: database.slow_query_threshold,
+        })
+    }
+
+    /**
+    Finish the transaction by either committing or rolling it back
+     */
+    fn finish<'p>(&mut self, py: Python<'p>, commit: bool) -> PyResult<&'p PyAny> {
+        let sender = self.sender.take().ok_or_else(out_of_sync)?;
+        pyo3_asyncio::tokio::future_into_py(py, finish(sender, commit))
+    }
+}
+
+#[pymethods]
+impl Transaction {
+    /**
+    Create a new cursor whose queries are executed within this transaction
+     */
+    fn cursor(slf: PyRef<Self>, py: Python) -> cursor::Cursor {
+        let database = slf.database.clone_ref(py);
+        cursor::Cursor::new(database, Some(slf.into()))
+    }
+
+    /**
+    Create a savepoint within the transaction, the returned awaitable resolves to a Savepoint
+
+    A unique name is generated when none is given.
+     */
+    fn savepoint<'p>(
+        mut slf: PyRefMut<'p, Self>,
+        py: Python<'p>,
+        name: Option<String>,
+    ) -> PyResult<&'p PyAny> {
+        let name = match name {
+            Some(name) => {
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return Err(errors::ProgrammingError::new_err(format!(
+                        "Invalid savepoint name {}",
+                        name
+                    )));
+                }
+                name
+            }
+            None => {
+                slf.savepoints += 1;
+                format!("rorm_savepoint_{}", slf.savepoints)
+            }
+        };
+        let executor = slf.executor(py)?;
+        let transaction: Py<Transaction> = slf.into();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            executor
+                .raw_sql(format!("SAVEPOINT {}", name), vec![])
+                .await?;
+            Ok(Savepoint {
+                transaction,
+                name,
+                released: false,
+            })
+        })
+    }
+
+    /**
+    Commit the transaction
+     */
+    fn commit<'p>(&mut self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        self.finish(py, true)
+    }
+
+    /**
+    Roll back the transaction
+     */
+    fn rollback<'p>(&mut self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        self.finish(py, false)
+    }
+
+    /**
+    Enter the transaction as async context manager, use it as `async with await db.begin() as tx:`
+     */
+    fn __aenter__<'p>(slf: PyRef<'p, Self>, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let transaction: Py<Transaction> = slf.into();
+        pyo3_asyncio::tokio::future_into_py(py, async move { Ok(transaction) })
+    }
+
+    /**
+    Commit the transaction when the block succeeded or roll it back when an exception propagated
+
+    Transactions which were already finished within the block are left untouched.
+     */
+    fn __aexit__<'p>(
+        &mut self,
+        py: Python<'p>,
+        exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> PyResult<&'p PyAny> {
+        if self.sender.is_none() {
+            return pyo3_asyncio::tokio::future_into_py(py, async move { Ok(()) });
+        }
+        self.finish(py, exc_type.is_none())
+    }
+}
+
+/**
+Savepoint within a running transaction, which is created by `Transaction.savepoint`
+ */
+#[pyclass(module = "rorm_python")]
+pub(crate) struct Savepoint {
+    transaction: Py<Transaction>,
+    #[pyo3(get)]
+    name: String,
+    released: bool,
+}
+
+impl Savepoint {
+    /**
+    Execute a statement referring to this savepoint within its transaction
+     */
+    fn execute<'p>(&self, py: Python<'p>, statement: &str) -> PyResult<&'p PyAny> {
+        if self.released {
+            return Err(errors::ProgrammingError::new_err(format!(
+                "Savepoint {} has already been released",
+                self.name
+            )));
+        }
+        let executor = self.transaction.borrow(py).executor(py)?;
+        let query = format!("{} {}", statement, self.name);
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            executor.raw_sql(query, vec![]).await?;
+            Ok(())
+        })
+    }
+}
+
+#[pymethods]
+impl Savepoint {
+    /**
+    Release the savepoint, keeping all changes made since it has been created
+     */
+    fn release<'p>(&mut self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let awaitable = self.execute(py, "RELEASE SAVEPOINT")?;
+        self.released = true;
+        Ok(awaitable)
+    }
+
+    /**
+    Roll back all changes made since the savepoint has been created, the savepoint stays usable
+     */
+    fn rollback_to<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        self.execute(py, "ROLLBACK TO SAVEPOINT")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isolation_statement_for_postgres() {
+        assert_eq!(
+            isolation_statement(common::Backend::Postgres, " read committed").unwrap(),
+            Some("SET TRANSACTION ISOLATION LEVEL READ COMMITTED".to_string())
+        );
+        assert!(isolation_statement(common::Backend::Postgres, "SNAPSHOT").is_err());
+    }
+
+    #[test]
+    fn isolation_statement_for_sqlite_and_mysql() {
+        assert_eq!(
+            isolation_statement(common::Backend::SQLite, "serializable").unwrap(),
+            None
+        );
+        assert!(isolation_statement(common::Backend::SQLite, "READ COMMITTED").is_err());
+        assert!(isolation_statement(common::Backend::MySQL, "SERIALIZABLE").is_err());
+    }
+}
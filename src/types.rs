@@ -0,0 +1,523 @@
+use chrono::{
+    DateTime, Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc,
+};
+use pyo3::basic::CompareOp;
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyBytes, PyDate, PyDateTime, PyString, PyTime};
+use rorm::row::Row;
+
+use crate::common::{Backend, DatabaseValueType};
+use crate::errors;
+
+/**
+Type object of the DB API comparing equal to all type codes of its group
+ */
+#[pyclass(module = "rorm_python.bindings")]
+pub(crate) struct DBAPITypeObject {
+    name: &'static str,
+    values: Vec<DatabaseValueType>,
+}
+
+#[pymethods]
+impl DBAPITypeObject {
+    fn __richcmp__(&self, py: Python, other: &PyAny, op: CompareOp) -> PyObject {
+        let other = match other.extract::<DatabaseValueType>() {
+            Ok(other) => other,
+            Err(_) => return py.NotImplemented(),
+        };
+        match op {
+            CompareOp::Eq => self.values.contains(&other).into_py(py),
+            CompareOp::Ne => (!self.values.contains(&other)).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __repr__(&self) -> &'static str {
+        self.name
+    }
+}
+
+/**
+Convert a chrono date to a Python date
+ */
+pub(crate) fn date_to_py(py: Python, v: NaiveDate) -> PyResult<&PyDate> {
+    PyDate::new(py, v.year(), v.month() as u8, v.day() as u8)
+}
+
+/**
+Convert a chrono time to a Python time, truncating it to microseconds
+ */
+pub(crate) fn time_to_py(py: Python, v: NaiveTime) -> PyResult<&PyTime> {
+    PyTime::new(
+        py,
+        v.hour() as u8,
+        v.minute() as u8,
+        v.second() as u8,
+        (v.nanosecond() / 1000).min(999_999),
+        None,
+    )
+}
+
+/**
+Convert a chrono datetime to a naive Python datetime, truncating it to microseconds
+ */
+pub(crate) fn datetime_to_py(py: Python, v: NaiveDateTime) -> PyResult<&PyDateTime> {
+    PyDateTime::new(
+        py,
+        v.year(),
+        v.month() as u8,
+        v.day() as u8,
+        v.hour() as u8,
+        v.minute() as u8,
+        v.second() as u8,
+        (v.nanosecond() / 1000).min(999_999),
+        None,
+    )
+}
+
+/**
+Retrieve the `decimal.Decimal` class
+ */
+pub(crate) fn decimal_type(py: Python) -> PyResult<&PyAny> {
+    py.import("decimal")?.getattr("Decimal")
+}
+
+/**
+Convert the exact string representation of a decimal number to a Python `decimal.Decimal`
+ */
+pub(crate) fn decimal_to_py(py: Python, v: &str) -> PyResult<PyObject> {
+    Ok(decimal_type(py)?.call1((v,))?.into_py(py))
+}
+
+/**
+Decode a decimal column to the text representation of its value
+
+sqlx's Any driver can't decode DECIMAL and NUMERIC columns while its SQLite driver is enabled,
+which rorm requires. Instead, the value is decoded as text, i.e. the column has to be cast to
+text in the query on Postgres and MySQL, see `cast_to_text`. SQLite stores decimals as integer
+or real numbers, which are accepted as well.
+ */
+pub(crate) fn decimal_text(row: &Row, col: &str, backend: Backend) -> PyResult<Option<String>> {
+    if let Ok(v) = row.get::<Option<&str>, &str>(col) {
+        return Ok(v.map(|v| v.to_string()));
+    }
+    if let Ok(v) = row.get::<Option<i64>, &str>(col) {
+        return Ok(v.map(|v| v.to_string()));
+    }
+    match row.get::<Option<f64>, &str>(col) {
+        Ok(v) => Ok(v.map(|v| v.to_string())),
+        Err(rorm::error::Error::SqlxError(sqlx::Error::ColumnNotFound(_))) => {
+            Err(errors::ProgrammingError::new_err(format!(
+                "Column {} doesn't exist in the result set",
+                col
+            )))
+        }
+        Err(_) => Err(errors::DataError::new_err(format!(
+            "Can't decode column {} as Decimal, cast it to text in the query, e.g. {}",
+            col,
+            cast_to_text(backend, col)
+        ))),
+    }
+}
+
+/**
+Build the expression casting a column to text for the error hints, MySQL only accepts CHAR
+ */
+pub(crate) fn cast_to_text(backend: Backend, col: &str) -> String {
+    match backend {
+        Backend::MySQL => format!("CAST({} AS CHAR)", col),
+        Backend::SQLite | Backend::Postgres => format!("CAST({} AS TEXT)", col),
+    }
+}
+
+/**
+Decode an unsigned integer column
+
+sqlx's Any driver has no unsigned types and MySQL's unsigned columns can't be decoded as i64,
+so they have to be cast to text in the query, see `cast_to_text`, and are parsed instead.
+Integer columns of the other backends are accepted as well, as long as they aren't negative.
+ */
+pub(crate) fn unsigned_value(row: &Row, col: &str, backend: Backend) -> PyResult<Option<u64>> {
+    if let Ok(v) = row.get::<Option<i64>, &str>(col) {
+        return v.map(|v| parse_unsigned(col, &v.to_string())).transpose();
+    }
+    match row.get::<Option<&str>, &str>(col) {
+        Ok(v) => v.map(|v| parse_unsigned(col, v)).transpose(),
+        Err(rorm::error::Error::SqlxError(sqlx::Error::ColumnNotFound(_))) => {
+            Err(errors::ProgrammingError::new_err(format!(
+                "Column {} doesn't exist in the result set",
+                col
+            )))
+        }
+        Err(_) => Err(errors::NotSupportedError::new_err(format!(
+            "Can't decode unsigned column {}, cast it to text in the query, e.g. {}",
+            col,
+            cast_to_text(backend, col)
+        ))),
+    }
+}
+
+/**
+Parse the text of an unsigned integer, raising DataError for negative or malformed values
+ */
+pub(crate) fn parse_unsigned(col: &str, text: &str) -> PyResult<u64> {
+    text.trim().parse::<u64>().map_err(|_| {
+        errors::DataError::new_err(format!(
+            "Value {} of column {} isn't an unsigned integer",
+            text, col
+        ))
+    })
+}
+
+/**
+Retrieve the `uuid.UUID` class
+ */
+pub(crate) fn uuid_type(py: Python) -> PyResult<&PyAny> {
+    py.import("uuid")?.getattr("UUID")
+}
+
+/**
+Convert a UUID to a Python `uuid.UUID`
+ */
+pub(crate) fn uuid_to_py(py: Python, v: &uuid::Uuid) -> PyResult<PyObject> {
+    let kwargs = [("bytes", PyBytes::new(py, v.as_bytes()))].into_py_dict(py);
+    Ok(uuid_type(py)?.call((), Some(kwargs))?.into_py(py))
+}
+
+/**
+Convert the text of a JSON value to a Python object, or keep it as str if it shouldn't be parsed
+ */
+pub(crate) fn json_to_py(py: Python, text: &str, parse: bool) -> PyResult<PyObject> {
+    if !parse {
+        return Ok(text.into_py(py));
+    }
+    Ok(py
+        .import("json")?
+        .call_method1("loads", (text,))?
+        .into_py(py))
+}
+
+/**
+Serialize a Python object to the text of a JSON value
+ */
+pub(crate) fn py_to_json(obj: &PyAny) -> PyResult<String> {
+    obj.py()
+        .import("json")?
+        .call_method1("dumps", (obj,))?
+        .extract()
+}
+
+/**
+Wrapper of a Python object which is bound as its JSON serialization
+
+Dicts are bound as JSON anyway, but lists and tuples are expanded to one placeholder per item,
+so JSON arrays have to be wrapped, e.g. `Json([1, 2])`.
+ */
+#[pyclass(module = "rorm_python.bindings")]
+pub(crate) struct Json {
+    #[pyo3(get)]
+    obj: PyObject,
+}
+
+#[pymethods]
+impl Json {
+    #[new]
+    fn new(obj: PyObject) -> Self {
+        Json { obj }
+    }
+}
+
+impl Json {
+    /**
+    Serialize the wrapped object to the text of a JSON value
+     */
+    pub(crate) fn dumps(&self, py: Python) -> PyResult<String> {
+        py_to_json(self.obj.as_ref(py))
+    }
+}
+
+/**
+Emit a Warning about the truncation of a value, which can be escalated with the `warnings` filters
+ */
+pub(crate) fn warn_truncation(py: Python, message: &str) -> PyResult<()> {
+    PyErr::warn(py, py.get_type::<errors::Warning>(), message, 1)
+}
+
+/**
+Warn when the nanoseconds of a decoded time are truncated to Python's microsecond precision
+ */
+pub(crate) fn check_precision(py: Python, col: &str, nanos: u32) -> PyResult<()> {
+    if nanos % 1000 != 0 {
+        warn_truncation(
+            py,
+            &format!("Value of column {} truncated to microsecond precision", col),
+        )?;
+    }
+    Ok(())
+}
+
+/**
+Convert a decoded integer of a column with a narrower declared type to a Python int
+
+Values which don't fit into the declared type emit a Warning, but are returned unchanged.
+ */
+pub(crate) fn check_int_range<T, V>(
+    py: Python,
+    col: &str,
+    col_t: DatabaseValueType,
+    value: Option<V>,
+) -> PyResult<PyObject>
+where
+    T: TryFrom<V>,
+    V: Copy + std::fmt::Display + IntoPy<PyObject>,
+{
+    let value = match value {
+        Some(v) => v,
+        None => return Ok(py.None()),
+    };
+    if T::try_from(value).is_err() {
+        warn_truncation(
+            py,
+            &format!(
+                "Value {} of column {} doesn't fit into its type {:?}",
+                value, col, col_t
+            ),
+        )?;
+    }
+    Ok(value.into_py(py))
+}
+
+/**
+Retrieve the `datetime.timezone.utc` singleton
+ */
+pub(crate) fn utc(py: Python) -> PyResult<&PyAny> {
+    py.import("datetime")?.getattr("timezone")?.getattr("utc")
+}
+
+/**
+Convert a chrono UTC datetime to an aware Python datetime in UTC, truncating it to microseconds
+ */
+pub(crate) fn datetime_utc_to_py(py: Python, v: DateTime<Utc>) -> PyResult<&PyDateTime> {
+    let v = v.naive_utc();
+    PyDateTime::new(
+        py,
+        v.year(),
+        v.month() as u8,
+        v.day() as u8,
+        v.hour() as u8,
+        v.minute() as u8,
+        v.second() as u8,
+        (v.nanosecond() / 1000).min(999_999),
+        Some(&utc(py)?.into_py(py)),
+    )
+}
+
+/**
+Convert seconds since the epoch to a datetime in local time
+
+NaN, infinite and out of range ticks raise DataError, instead of saturating when converted to
+integers, which would silently result in the epoch or an arbitrary date.
+ */
+fn from_ticks(ticks: f64) -> PyResult<NaiveDateTime> {
+    // i64::MAX isn't exactly representable as f64, so the bounds are checked exclusively
+    let secs = ticks.floor();
+    if !secs.is_finite() || secs < i64::MIN as f64 || secs >= i64::MAX as f64 {
+        return Err(errors::DataError::new_err(format!(
+            "Ticks out of range: {}",
+            ticks
+        )));
+    }
+    // Rounding may result in a full second, which chrono would treat as leap second
+    let nanos = (((ticks - secs) * 1e9) as u32).min(999_999_999);
+    match Local.timestamp_opt(secs as i64, nanos).single() {
+        Some(v) => Ok(v.naive_local()),
+        None => Err(errors::DataError::new_err(format!(
+            "Ticks out of range: {}",
+            ticks
+        ))),
+    }
+}
+
+/**
+Construct a date value
+ */
+#[pyfunction(module = "rorm_python.bindings")]
+#[pyo3(name = "Date")]
+fn date(py: Python, year: i32, month: u8, day: u8) -> PyResult<&PyDate> {
+    PyDate::new(py, year, month, day)
+}
+
+/**
+Construct a time value
+ */
+#[pyfunction(module = "rorm_python.bindings")]
+#[pyo3(name = "Time")]
+fn time(py: Python, hour: u8, minute: u8, second: u8) -> PyResult<&PyTime> {
+    PyTime::new(py, hour, minute, second, 0, None)
+}
+
+/**
+Construct a timestamp value
+ */
+#[pyfunction(module = "rorm_python.bindings")]
+#[pyo3(name = "Timestamp")]
+fn timestamp(
+    py: Python,
+    year: i32,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+) -> PyResult<&PyDateTime> {
+    PyDateTime::new(py, year, month, day, hour, minute, second, 0, None)
+}
+
+/**
+Construct a date value from seconds since the epoch
+ */
+#[pyfunction(module = "rorm_python.bindings")]
+#[pyo3(name = "DateFromTicks")]
+fn date_from_ticks(py: Python, ticks: f64) -> PyResult<&PyDate> {
+    date_to_py(py, from_ticks(ticks)?.date())
+}
+
+/**
+Construct a time value from seconds since the epoch
+ */
+#[pyfunction(module = "rorm_python.bindings")]
+#[pyo3(name = "TimeFromTicks")]
+fn time_from_ticks(py: Python, ticks: f64) -> PyResult<&PyTime> {
+    time_to_py(py, from_ticks(ticks)?.time())
+}
+
+/**
+Construct a timestamp value from seconds since the epoch
+ */
+#[pyfunction(module = "rorm_python.bindings")]
+#[pyo3(name = "TimestampFromTicks")]
+fn timestamp_from_ticks(py: Python, ticks: f64) -> PyResult<&PyDateTime> {
+    datetime_to_py(py, from_ticks(ticks)?)
+}
+
+/**
+Construct a binary value, strings are encoded as UTF-8
+ */
+#[pyfunction(module = "rorm_python.bindings")]
+#[pyo3(name = "Binary")]
+fn binary<'p>(py: Python<'p>, string: &'p PyAny) -> PyResult<&'p PyBytes> {
+    if let Ok(v) = string.downcast::<PyString>() {
+        return Ok(PyBytes::new(py, v.to_str()?.as_bytes()));
+    }
+    Ok(PyBytes::new(py, string.extract::<&[u8]>()?))
+}
+
+pub(super) fn types(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(date, m)?)?;
+    m.add_function(wrap_pyfunction!(time, m)?)?;
+    m.add_function(wrap_pyfunction!(timestamp, m)?)?;
+    m.add_function(wrap_pyfunction!(date_from_ticks, m)?)?;
+    m.add_function(wrap_pyfunction!(time_from_ticks, m)?)?;
+    m.add_function(wrap_pyfunction!(timestamp_from_ticks, m)?)?;
+    m.add_function(wrap_pyfunction!(binary, m)?)?;
+    m.add_class::<Json>()?;
+
+    let groups = [
+        ("STRING", vec![DatabaseValueType::String]),
+        ("BINARY", vec![DatabaseValueType::Binary]),
+        ("JSON", vec![DatabaseValueType::Json]),
+        ("UUID", vec![DatabaseValueType::Uuid]),
+        (
+            "NUMBER",
+            vec![
+                DatabaseValueType::I64,
+                DatabaseValueType::I32,
+                DatabaseValueType::I16,
+                DatabaseValueType::U64,
+                DatabaseValueType::U32,
+                DatabaseValueType::F64,
+                DatabaseValueType::F32,
+                DatabaseValueType::Decimal,
+            ],
+        ),
+        (
+            "DATETIME",
+            vec![
+                DatabaseValueType::NaiveDate,
+                DatabaseValueType::NaiveTime,
+                DatabaseValueType::NaiveDateTime,
+                DatabaseValueType::DateTimeUtc,
+            ],
+        ),
+        // Row IDs are plain 64 bit integers on all supported databases
+        ("ROWID", vec![DatabaseValueType::I64]),
+    ];
+    for (name, values) in groups {
+        m.add(name, Py::new(_py, DBAPITypeObject { name, values })?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_ticks_rejects_invalid_ticks() {
+        assert!(from_ticks(f64::NAN).is_err());
+        assert!(from_ticks(f64::INFINITY).is_err());
+        assert!(from_ticks(f64::NEG_INFINITY).is_err());
+        assert!(from_ticks(1e300).is_err());
+    }
+
+    #[test]
+    fn cast_to_text_uses_char_on_mysql() {
+        assert_eq!(cast_to_text(Backend::MySQL, "price"), "CAST(price AS CHAR)");
+        assert_eq!(
+            cast_to_text(Backend::Postgres, "price"),
+            "CAST(price AS TEXT)"
+        );
+        assert_eq!(
+            cast_to_text(Backend::SQLite, "price"),
+            "CAST(price AS TEXT)"
+        );
+    }
+
+    #[test]
+    fn parse_unsigned_keeps_values_beyond_i64() {
+        assert_eq!(parse_unsigned("id", "0").unwrap(), 0);
+        assert_eq!(
+            parse_unsigned("id", "9223372036854775808").unwrap(),
+            i64::MAX as u64 + 1
+        );
+        assert_eq!(
+            parse_unsigned("id", "18446744073709551614").unwrap(),
+            u64::MAX - 1
+        );
+        assert_eq!(
+            parse_unsigned("id", "18446744073709551615").unwrap(),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn parse_unsigned_rejects_invalid_values() {
+        assert!(parse_unsigned("id", "18446744073709551616").is_err());
+        assert!(parse_unsigned("id", "-1").is_err());
+        assert!(parse_unsigned("id", "1.5").is_err());
+        assert!(parse_unsigned("id", "").is_err());
+    }
+
+    #[test]
+    fn from_ticks_keeps_the_fraction() {
+        let v = from_ticks(86400.5).unwrap();
+        assert_eq!(v.nanosecond(), 500_000_000);
+        assert_eq!(
+            v,
+            Local
+                .timestamp_opt(86400, 500_000_000)
+                .unwrap()
+                .naive_local()
+        );
+    }
+}
@@ -1,7 +1,10 @@
+use std::collections::BTreeMap;
+use std::env;
 use std::fs::read_to_string;
+use std::path::Path;
 
 use pyo3::prelude::*;
-use rorm::{config::DatabaseConfig, Database, DatabaseConfiguration};
+use rorm::{config::DatabaseConfig, DatabaseConfiguration};
 use serde::{Deserialize, Serialize};
 
 use crate::common;
@@ -9,10 +12,184 @@ use crate::errors;
 
 static DEFAULT_MAX_CONNECTIONS: u32 = 32;
 
+/**
+Config file with either a single `Database` section or several named `Databases.<name>` sections
+ */
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 struct ConfigFile {
-    database: DatabaseConfig,
+    database: Option<DatabaseConfig>,
+    #[serde(default)]
+    databases: BTreeMap<String, DatabaseConfig>,
+}
+
+impl ConfigFile {
+    /**
+    Select the database with the given name, which may be omitted if the file only has one database
+     */
+    fn select(self, name: Option<String>) -> PyResult<DatabaseConfig> {
+        let mut databases = self.databases;
+        if let Some(name) = name {
+            return match databases.remove(&name) {
+                Some(v) => Ok(v),
+                None => Err(errors::ProgrammingError::new_err(format!(
+                    "Config file has no database named {}, expected one of {}",
+                    name,
+                    databases.keys().cloned().collect::<Vec<_>>().join(", ")
+                ))),
+            };
+        }
+        match (self.database, databases.len()) {
+            (Some(v), 0) => Ok(v),
+            (None, 1) => Ok(databases.into_values().next().unwrap()),
+            (None, 0) => Err(errors::Error::new_err(
+                "Config file doesn't contain any database",
+            )),
+            (_, _) => Err(errors::ProgrammingError::new_err(format!(
+                "Config file contains multiple databases, choose one of {} by its name",
+                databases.keys().cloned().collect::<Vec<_>>().join(", ")
+            ))),
+        }
+    }
+}
+
+/**
+Expand all references to environment variables like `${DB_PASSWORD}` in a string of the config
+ */
+fn expand_env_vars(content: &str) -> PyResult<String> {
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let end = match rest[start..].find('}') {
+            Some(v) => start + v,
+            None => {
+                return Err(errors::Error::new_err(
+                    "Unterminated environment variable reference in config file",
+                ))
+            }
+        };
+        let name = &rest[start + 2..end];
+        match env::var(name) {
+            Ok(v) => result.push_str(&v),
+            Err(_) => {
+                return Err(errors::Error::new_err(format!(
+                    "Environment variable {} referenced in config file is not set",
+                    name
+                )))
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/**
+Expand the environment variables in all strings of a parsed TOML config
+ */
+fn expand_toml(value: &mut toml::Value) -> PyResult<()> {
+    match value {
+        toml::Value::String(v) => *v = expand_env_vars(v)?,
+        toml::Value::Array(values) => values.iter_mut().try_for_each(expand_toml)?,
+        toml::Value::Table(table) => table.iter_mut().try_for_each(|(_, v)| expand_toml(v))?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/**
+Expand the environment variables in all strings of a parsed JSON config
+ */
+fn expand_json(value: &mut serde_json::Value) -> PyResult<()> {
+    match value {
+        serde_json::Value::String(v) => *v = expand_env_vars(v)?,
+        serde_json::Value::Array(values) => values.iter_mut().try_for_each(expand_json)?,
+        serde_json::Value::Object(map) => map.iter_mut().try_for_each(|(_, v)| expand_json(v))?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/**
+Expand the environment variables in all strings of a parsed YAML config
+ */
+fn expand_yaml(value: &mut serde_yaml::Value) -> PyResult<()> {
+    match value {
+        serde_yaml::Value::String(v) => *v = expand_env_vars(v)?,
+        serde_yaml::Value::Sequence(values) => values.iter_mut().try_for_each(expand_yaml)?,
+        serde_yaml::Value::Mapping(map) => map.iter_mut().try_for_each(|(_, v)| expand_yaml(v))?,
+        serde_yaml::Value::Tagged(tagged) => expand_yaml(&mut tagged.value)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/**
+Parse the content of a config file in the given format, expanding environment variables
+
+The references are only expanded inside string values after parsing, so the values of the
+variables are never interpreted as part of the config syntax. Unknown formats are parsed as TOML.
+ */
+fn parse_config(content: &str, format: &str) -> PyResult<ConfigFile> {
+    let result = match format {
+        "yaml" | "yml" => {
+            let mut value: serde_yaml::Value = serde_yaml::from_str(content)
+                .map_err(|err| errors::Error::new_err(err.to_string()))?;
+            expand_yaml(&mut value)?;
+            serde_yaml::from_value(value).map_err(|err| err.to_string())
+        }
+        "json" => {
+            let mut value: serde_json::Value = serde_json::from_str(content)
+                .map_err(|err| errors::Error::new_err(err.to_string()))?;
+            expand_json(&mut value)?;
+            serde_json::from_value(value).map_err(|err| err.to_string())
+        }
+        _ => {
+            let mut value: toml::Value =
+                toml::from_str(content).map_err(|err| errors::Error::new_err(err.to_string()))?;
+            expand_toml(&mut value)?;
+            value.try_into().map_err(|err| err.to_string())
+        }
+    };
+    result.map_err(errors::Error::new_err)
+}
+
+/**
+Parse the config file in the given format, which is otherwise detected by the file extension
+
+Files with an unknown extension are parsed as TOML.
+ */
+fn parse_config_file(path: &str, format: Option<String>) -> PyResult<ConfigFile> {
+    let format = match format {
+        Some(v) => v.to_lowercase(),
+        None => Path::new(path)
+            .extension()
+            .and_then(|v| v.to_str())
+            .unwrap_or("toml")
+            .to_lowercase(),
+    };
+    parse_config(&read_to_string(path)?, &format)
+}
+
+/**
+Read the configuration of the selected database from the config file
+ */
+fn read_configuration(
+    path: &str,
+    format: Option<String>,
+    name: Option<String>,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+) -> PyResult<DatabaseConfiguration> {
+    let db_conf_file = parse_config_file(path, format)?.select(name)?;
+    Ok(DatabaseConfiguration {
+        driver: db_conf_file.driver.clone(),
+        min_connections: min_connections.or_else(|| Some(1)).unwrap(),
+        max_connections: max_connections
+            .or_else(|| Some(DEFAULT_MAX_CONNECTIONS))
+            .unwrap(),
+    })
 }
 
 #[pyfunction(module = "rorm_python.bindings.utils")]
@@ -21,28 +198,121 @@ fn connect_from_config(
     path: String,
     min_connections: Option<u32>,
     max_connections: Option<u32>,
+    format: Option<String>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+    name: Option<String>,
 ) -> PyResult<&PyAny> {
-    let db_conf_file: ConfigFile = match toml::from_str(&read_to_string(&path)?) {
-        Ok(v) => v,
-        Err(err) => return Err(errors::Error::new_err(err.to_string())),
-    };
-    let db_conf = DatabaseConfiguration {
-        driver: db_conf_file.database.driver.clone(),
-        min_connections: min_connections.or_else(|| Some(1)).unwrap(),
-        max_connections: max_connections
-            .or_else(|| Some(DEFAULT_MAX_CONNECTIONS))
-            .unwrap(),
-    };
+    let db_conf = read_configuration(&path, format, name, min_connections, max_connections)?;
     pyo3_asyncio::tokio::future_into_py(py, async move {
-        match Database::connect(db_conf).await {
-            Ok(v) => Ok(common::Database { db: Box::new(v) }),
-            Err(err) => Err(errors::DatabaseError::new_err(err.to_string())),
-        }
+        common::Database::connect(db_conf, connect_timeout, connect_retries, retry_backoff_ms).await
     })
 }
 
+/**
+Connect to the database of the config file like `connect_from_config`, but block until connected
+ */
+#[pyfunction(module = "rorm_python.bindings.utils")]
+fn connect_from_config_blocking(
+    py: Python<'_>,
+    path: String,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+    format: Option<String>,
+    connect_timeout: Option<f64>,
+    connect_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+    name: Option<String>,
+) -> PyResult<common::Database> {
+    let db_conf = read_configuration(&path, format, name, min_connections, max_connections)?;
+    common::block_on(
+        py,
+        common::Database::connect(db_conf, connect_timeout, connect_retries, retry_backoff_ms),
+    )
+}
+
 #[pymodule]
 pub(super) fn utils(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(connect_from_config, m)?)?;
+    m.add_function(wrap_pyfunction!(connect_from_config_blocking, m)?)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use rorm::DatabaseDriver;
+
+    use super::*;
+
+    fn filename(config: DatabaseConfig) -> String {
+        match config.driver {
+            DatabaseDriver::SQLite { filename } => filename,
+            _ => panic!("Expected a SQLite driver"),
+        }
+    }
+
+    #[test]
+    fn select_single_database() {
+        let config: ConfigFile =
+            toml::from_str("[Database]\nDriver = \"SQLite\"\nFilename = \"main.db\"\n").unwrap();
+        assert_eq!(filename(config.select(None).unwrap()), "main.db");
+    }
+
+    #[test]
+    fn select_named_database() {
+        let content = "[Databases.main]\nDriver = \"SQLite\"\nFilename = \"main.db\"\n\
+            [Databases.logs]\nDriver = \"SQLite\"\nFilename = \"logs.db\"\n";
+        let config: ConfigFile = toml::from_str(content).unwrap();
+        assert_eq!(
+            filename(config.select(Some("logs".to_string())).unwrap()),
+            "logs.db"
+        );
+        let config: ConfigFile = toml::from_str(content).unwrap();
+        assert!(config.select(None).is_err());
+        let config: ConfigFile = toml::from_str(content).unwrap();
+        assert!(config.select(Some("other".to_string())).is_err());
+    }
+
+    #[test]
+    fn select_without_databases() {
+        let config: ConfigFile = toml::from_str("").unwrap();
+        assert!(config.select(None).is_err());
+    }
+
+    #[test]
+    fn expand_env_vars_replaces_references() {
+        env::set_var("DRORM_BIND_TEST_PASSWORD", "secret");
+        assert_eq!(
+            expand_env_vars("Password = \"${DRORM_BIND_TEST_PASSWORD}\"").unwrap(),
+            "Password = \"secret\""
+        );
+        assert_eq!(expand_env_vars("Port = 5432").unwrap(), "Port = 5432");
+    }
+
+    #[test]
+    fn parse_config_expands_only_inside_strings() {
+        env::set_var(
+            "DRORM_BIND_TEST_FILENAME",
+            "main.db\"\n[Databases.injected]\nDriver = \"SQLite\"\nFilename = \"x.db",
+        );
+        let expected = env::var("DRORM_BIND_TEST_FILENAME").unwrap();
+        let toml = "[Database]\nDriver = \"SQLite\"\nFilename = \"${DRORM_BIND_TEST_FILENAME}\"\n";
+        let config = parse_config(toml, "toml").unwrap();
+        assert!(config.databases.is_empty());
+        assert_eq!(filename(config.select(None).unwrap()), expected);
+        let json = "{\"Database\": {\"Driver\": \"SQLite\", \
+            \"Filename\": \"${DRORM_BIND_TEST_FILENAME}\"}}";
+        let config = parse_config(json, "json").unwrap();
+        assert_eq!(filename(config.select(None).unwrap()), expected);
+        let yaml = "Database:\n  Driver: SQLite\n  Filename: \"${DRORM_BIND_TEST_FILENAME}\"\n";
+        let config = parse_config(yaml, "yaml").unwrap();
+        assert_eq!(filename(config.select(None).unwrap()), expected);
+    }
+
+    #[test]
+    fn expand_env_vars_rejects_invalid_references() {
+        assert!(expand_env_vars("${DRORM_BIND_TEST_UNSET_VARIABLE}").is_err());
+        assert!(expand_env_vars("Password = \"${DRORM_BIND_TEST_PASSWORD\"").is_err());
+    }
+}
@@ -0,0 +1,18 @@
+import asyncio
+
+import pytest
+
+from rorm_python.bindings import sqlite
+
+
+@pytest.fixture
+def db():
+    """New in-memory SQLite database, which is closed after the test"""
+
+    async def connect():
+        return await sqlite.connect_in_memory()
+
+    database = asyncio.run(connect())
+    yield database
+    if not database.is_closed:
+        database.close_blocking()
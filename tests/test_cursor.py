@@ -0,0 +1,99 @@
+import asyncio
+import datetime
+
+import pytest
+
+from rorm_python.bindings import errors
+
+
+async def create_table(db, sql):
+    cursor = db.cursor()
+    await cursor.execute(sql)
+    return cursor
+
+
+def test_fetch_one_single_row(db):
+    async def main():
+        cursor = db.cursor()
+        await cursor.execute("SELECT 1 AS a", columns=["a"])
+        assert cursor.fetch_one() == (1,)
+        assert cursor.fetch_one() is None
+
+    asyncio.run(main())
+
+
+def test_fetch_one_multiple_rows(db):
+    async def main():
+        cursor = await create_table(db, "CREATE TABLE t (id INTEGER, name TEXT)")
+        await cursor.executemany("INSERT INTO t VALUES (?, ?)", [[1, "a"], [2, "b"]])
+        cursor.row_factory = "dict"
+        await cursor.execute("SELECT id, name FROM t ORDER BY id", columns=["id", "name"])
+        assert cursor.fetch_one() == {"id": 1, "name": "a"}
+        assert cursor.fetch_one() == {"id": 2, "name": "b"}
+        assert cursor.fetch_one() is None
+
+    asyncio.run(main())
+
+
+def test_fetch_one_empty_result(db):
+    async def main():
+        cursor = await create_table(db, "CREATE TABLE t (id INTEGER)")
+        assert cursor.rowcount == -1
+        await cursor.execute("SELECT id FROM t", columns=["id"])
+        assert cursor.fetch_one() is None
+
+    asyncio.run(main())
+
+
+def test_fetch_one_without_query(db):
+    with pytest.raises(errors.ProgrammingError):
+        db.cursor().fetch_one()
+
+
+def test_rows_without_columns(db):
+    async def main():
+        cursor = db.cursor()
+        with pytest.raises(errors.ProgrammingError):
+            await cursor.execute("SELECT 1")
+
+    asyncio.run(main())
+
+
+def test_binary_round_trip(db):
+    async def main():
+        cursor = await create_table(db, "CREATE TABLE t (id INTEGER, data BLOB)")
+        blob = bytes(range(256)) * 4096
+        await cursor.executemany(
+            "INSERT INTO t VALUES (?, ?)",
+            [[1, blob], [2, memoryview(blob)], [3, bytearray(blob)], [4, None]],
+        )
+        await cursor.execute("SELECT data FROM t ORDER BY id", columns=[("data", "Binary")])
+        assert cursor.fetch_all() == [(blob,), (blob,), (blob,), (None,)]
+
+    asyncio.run(main())
+
+
+def test_date_and_time_round_trip(db):
+    async def main():
+        cursor = await create_table(db, "CREATE TABLE t (d TEXT, t TEXT, dt TEXT)")
+        date = datetime.date(2022, 2, 28)
+        time = datetime.time(23, 59, 58, 123456)
+        timestamp = datetime.datetime(1999, 12, 31, 23, 59, 59, 999999)
+        await cursor.execute("INSERT INTO t VALUES (?, ?, ?)", [date, time, timestamp])
+        await cursor.execute("INSERT INTO t VALUES (NULL, NULL, NULL)")
+        columns = [("d", "NaiveDate"), ("t", "NaiveTime"), ("dt", "NaiveDateTime")]
+        await cursor.execute("SELECT d, t, dt FROM t", columns=columns)
+        assert cursor.fetch_all() == [(date, time, timestamp), (None, None, None)]
+
+    asyncio.run(main())
+
+
+def test_executemany_rowcount(db):
+    async def main():
+        cursor = await create_table(db, "CREATE TABLE t (id INTEGER)")
+        await cursor.executemany("INSERT INTO t VALUES (?)", [[v] for v in range(1000)])
+        assert cursor.rowcount == -1
+        await cursor.execute("SELECT COUNT(*) AS count FROM t", columns=["count"])
+        assert cursor.fetch_one() == (1000,)
+
+    asyncio.run(main())
@@ -0,0 +1,64 @@
+import asyncio
+
+import pytest
+
+from rorm_python.bindings import errors
+
+
+async def count_rows(db):
+    cursor = db.cursor()
+    await cursor.execute("SELECT COUNT(*) AS count FROM t", columns=["count"])
+    return cursor.fetch_one()[0]
+
+
+def test_transaction_commits_on_success(db):
+    async def main():
+        await db.cursor().execute("CREATE TABLE t (id INTEGER)")
+        async with await db.begin() as tx:
+            await tx.cursor().execute("INSERT INTO t VALUES (1)")
+        assert await count_rows(db) == 1
+
+    asyncio.run(main())
+
+
+def test_transaction_rolls_back_on_exception(db):
+    async def main():
+        await db.cursor().execute("CREATE TABLE t (id INTEGER)")
+        with pytest.raises(RuntimeError):
+            async with await db.begin() as tx:
+                await tx.cursor().execute("INSERT INTO t VALUES (1)")
+                raise RuntimeError("abort")
+        assert await count_rows(db) == 0
+
+    asyncio.run(main())
+
+
+def test_context_manager_closes_the_database(db):
+    async def main():
+        async with db as database:
+            assert database is db
+        assert db.is_closed
+
+    asyncio.run(main())
+
+
+def test_context_manager_closes_the_database_on_exception(db):
+    async def main():
+        with pytest.raises(RuntimeError):
+            async with db:
+                raise RuntimeError("abort")
+        assert db.is_closed
+        with pytest.raises(errors.InterfaceError):
+            db.cursor()
+
+    asyncio.run(main())
+
+
+def test_ping(db):
+    async def main():
+        assert await db.ping() is True
+        await db.close()
+        with pytest.raises(errors.InterfaceError):
+            await db.ping()
+
+    asyncio.run(main())
@@ -0,0 +1,59 @@
+import asyncio
+import os
+
+import pytest
+
+from rorm_python import bindings
+from rorm_python.bindings import errors
+
+
+def test_exception_classes():
+    assert errors.ProgrammingError is not errors.InterfaceError
+    assert errors.NotSupportedError is not errors.InterfaceError
+    assert issubclass(errors.ProgrammingError, errors.DatabaseError)
+    assert issubclass(errors.NotSupportedError, errors.DatabaseError)
+    assert issubclass(errors.InterfaceError, errors.Error)
+    assert issubclass(errors.Warning, Warning)
+    assert issubclass(errors.Error, Exception)
+
+
+CREATE_TABLE = "CREATE TABLE pytest_unique (id INTEGER PRIMARY KEY)"
+
+
+async def insert_duplicate(db):
+    cursor = db.cursor()
+    await cursor.execute(CREATE_TABLE)
+    await cursor.execute("INSERT INTO pytest_unique VALUES (1)")
+    try:
+        with pytest.raises(errors.IntegrityError) as info:
+            await cursor.execute("INSERT INTO pytest_unique VALUES (1)")
+    finally:
+        await cursor.execute("DROP TABLE pytest_unique")
+    return info.value
+
+
+def test_integrity_error_on_sqlite(db):
+    error = asyncio.run(insert_duplicate(db))
+    assert error.sqlstate is None
+    assert error.args == (str(error),)
+
+
+@pytest.mark.parametrize(
+    "variable,sqlstate",
+    [("DRORM_TEST_POSTGRES_DSN", "23505"), ("DRORM_TEST_MYSQL_DSN", "23000")],
+)
+def test_sqlstate_of_integrity_errors(variable, sqlstate):
+    dsn = os.environ.get(variable)
+    if dsn is None:
+        pytest.skip(f"{variable} isn't set")
+
+    async def main():
+        db = await bindings.connect(dsn)
+        try:
+            return await insert_duplicate(db)
+        finally:
+            await db.close()
+
+    error = asyncio.run(main())
+    assert error.sqlstate == sqlstate
+    assert error.args == (str(error),)